@@ -1,58 +1,348 @@
 use std::collections::HashMap;
 
+use diaparser::{ClassKind, RelationshipKind, Visibility};
 use inline_xml::{xml_tag, Tag};
 
-use crate::{clean_ast::{Attribute, Class, Method}, style::Style};
+use crate::{clean_ast::{resolve_name, unqualified_name, Attribute, Class, Enum, Method, Note, Package, Relationship, VariantPayload}, style::{Alignment, Style, VerticalAlignment, FONT_STYLE_BOLD, FONT_STYLE_ITALIC, FONT_STYLE_UNDERLINE}, xml::{escape_html, escape_xml}};
 
 const START_HEIGHT: u32 = 26;
+const STEREOTYPE_START_HEIGHT: u32 = 40;
+const VARIANT_HEIGHT: u32 = 26;
 const ATTR_HEIGHT: u32 = 26;
 const SEPARATOR_HEIGHT: u32 = 8;
 const METHOD_HEIGHT: u32 = 26;
 const CLASS_WIDTH: u32 = 230;
+const PACKAGE_START_HEIGHT: u32 = 40;
+const Y_PADDING: u32 = 30;
+const X_PADDING: u32 = 15;
+const NOTE_WIDTH: u32 = 180;
+const NOTE_LINE_HEIGHT: u32 = 20;
+/// Characters that fit on one line of a note, used to estimate its height
+const NOTE_LINE_LENGTH: u32 = 25;
 
-pub(crate) fn make_class_diag(classes: &HashMap<String, Class>) -> Vec<String> {
+/// Where a box goes, `parent` is the id of the page or package containing it
+#[derive(Clone, Copy)]
+struct Placement<'a> {
+    parent: &'a str,
+    x: u32,
+    y: u32
+}
+
+/// What the diagram shows, packages are laid out with the elements they contain
+#[derive(Clone, Copy)]
+struct Elements<'a> {
+    classes: &'a HashMap<String, Class>,
+    enums: &'a HashMap<String, Enum>,
+    packages: &'a [Package],
+    notes: &'a [Note]
+}
+
+/// Label, font style and doc of an attribute or a method
+type MemberRow = (String, u32, Option<String>);
+
+/// A class as it is drawn, `name` is unqualified
+struct ClassDescription<'a> {
+    name: &'a str,
+    kind: ClassKind,
+    is_abstract: bool,
+    doc: Option<&'a str>,
+    attributes: Vec<MemberRow>,
+    methods: Vec<MemberRow>
+}
+
+/// The cells of a class and the ids of its members, edges can be attached to them
+struct ClassCells {
+    cells: String,
+    height: u32,
+    attribute_ids: Vec<String>,
+    method_ids: Vec<String>
+}
+
+/// Ids of the cells edges can be attached to
+#[derive(Default)]
+struct CellIds<'a> {
+    classes: HashMap<&'a str, String>,
+    /// Overloaded methods share their name, so a name can have several cells
+    members: HashMap<&'a str, HashMap<&'a str, Vec<String>>>,
+    notes: Vec<(&'a Note, String)>,
+}
+
+pub(crate) fn make_class_diag(classes: &HashMap<String, Class>, enums: &HashMap<String, Enum>, packages: &[Package], relationships: &[Relationship], notes: &[Note]) -> Vec<String> {
     let mut id = 2;
     let mut y = 25;
 
-    class_diag_from_classes(&mut id, &mut y, classes)
+    class_diag_from_classes(&mut id, &mut y, classes, enums, packages, relationships, notes)
 }
 
-fn class_diag_from_classes(id: &mut u32, y: &mut u32, classes: &HashMap<String, Class>) -> Vec<String> {
-    const Y_PADDING: u32 = 30;
-    const X_PADDING: u32 = 15;
-    let mut ret = Vec::new();
+fn class_diag_from_classes(id: &mut u32, y: &mut u32, classes: &HashMap<String, Class>, enums: &HashMap<String, Enum>, packages: &[Package], relationships: &[Relationship], notes: &[Note]) -> Vec<String> {
+    let mut ids = CellIds::default();
+    let elements = Elements { classes, enums, packages, notes };
+    let (mut ret, _) = make_package_content(id, y, None, "class-diag-1", elements, &mut ids);
+    let class_ids = &ids.classes;
+
     for class in classes.values() {
-        let Class { name, attributes, methods } = class;
-        let attributes = get_attributes(attributes);
-        let methods = get_methods(methods);
-        let (a, y_tmp) = make_class(id, name, attributes, methods, X_PADDING, *y);
+        let source = &class_ids[class.name.as_str()];
+        let package = class.package.as_deref();
+        for parent in &class.parents {
+            match resolve_name(package, parent, |name| class_ids.contains_key(name)).map(|parent| &class_ids[parent.as_str()]) {
+                Some(target) => {
+                    ret.push(make_edge(
+                        format!("class-diag-{id}"),
+                        Style::default_generalization(),
+                        "class-diag-1",
+                        source,
+                        target
+                    ).to_string());
+                    *id += 1;
+                },
+                None => eprintln!("Class {} extends {}, but {} is not defined", class.name, parent, parent),
+            }
+        }
+
+        for interface in &class.interfaces {
+            match resolve_name(package, interface, |name| class_ids.contains_key(name)).map(|interface| &class_ids[interface.as_str()]) {
+                Some(target) => {
+                    ret.push(make_edge(
+                        format!("class-diag-{id}"),
+                        Style::default_realization(),
+                        "class-diag-1",
+                        source,
+                        target
+                    ).to_string());
+                    *id += 1;
+                },
+                None => eprintln!("Class {} implements {}, but {} is not defined", class.name, interface, interface),
+            }
+        }
+    }
+
+    for relationship in relationships {
+        let package = relationship.package.as_deref();
+        let source = resolve_name(package, &relationship.source, |name| class_ids.contains_key(name));
+        let target = resolve_name(package, &relationship.target, |name| class_ids.contains_key(name));
+        match (source.map(|source| &class_ids[source.as_str()]), target.map(|target| &class_ids[target.as_str()])) {
+            (Some(source), Some(target)) => {
+                let style = match relationship.kind {
+                    RelationshipKind::Association => Style::default_association(),
+                    RelationshipKind::DirectedAssociation => Style::default_directed_association(),
+                    RelationshipKind::Aggregation => Style::default_aggregation(),
+                    RelationshipKind::Composition => Style::default_composition(),
+                    RelationshipKind::Dependency => Style::default_dependency(),
+                };
+                let edge_id = format!("class-diag-{id}");
+                ret.push(make_edge(
+                    edge_id.clone(),
+                    style,
+                    "class-diag-1",
+                    source,
+                    target
+                ).to_string());
+                *id += 1;
+
+                let labels = [
+                    (&relationship.source_label, -1.0, Alignment::Left),
+                    (&relationship.name, 0.0, Alignment::Center),
+                    (&relationship.target_label, 1.0, Alignment::Right),
+                ];
+                for (label, x, alignment) in labels {
+                    if let Some(label) = label {
+                        ret.push(make_edge_label(
+                            format!("class-diag-{id}"),
+                            &edge_id,
+                            label,
+                            x,
+                            alignment
+                        ).to_string());
+                        *id += 1;
+                    }
+                }
+            },
+            (None, _) => eprintln!("Cannot relate {} to {}, {} is not defined", relationship.source, relationship.target, relationship.source),
+            (_, None) => eprintln!("Cannot relate {} to {}, {} is not defined", relationship.source, relationship.target, relationship.target),
+        }
+    }
+
+    for (note, note_id) in &ids.notes {
+        let Some(target) = &note.target else {
+            continue
+        };
+        let class = resolve_name(note.package.as_deref(), &target.class, |name| class_ids.contains_key(name));
+        let target_ids = match (class, &target.member) {
+            (Some(class), None) => std::slice::from_ref(&class_ids[class.as_str()]),
+            (Some(class), Some(member)) => match ids.members.get(class.as_str()).and_then(|members| members.get(member.as_str())) {
+                Some(member_ids) => member_ids.as_slice(),
+                None => {
+                    eprintln!("Cannot attach note to {}.{}, {} has no member {}", target.class, member, target.class, member);
+                    continue
+                },
+            },
+            (None, _) => {
+                eprintln!("Cannot attach note to {}, {} is not defined", target.class, target.class);
+                continue
+            },
+        };
+        for target_id in target_ids {
+            ret.push(make_edge(
+                format!("class-diag-{id}"),
+                Style::default_note_link(),
+                "class-diag-1",
+                note_id,
+                target_id
+            ).to_string());
+            *id += 1;
+        }
+    }
+
+    ret
+}
+
+/// Lays out the enums, classes and packages directly inside `package` from top to bottom, starting at `y`,
+/// with the notes in a column on their right.
+/// Returns the cells and the width they take
+fn make_package_content<'a>(id: &mut u32, y: &mut u32, package: Option<&str>, parent: &str, elements: Elements<'a>, ids: &mut CellIds<'a>) -> (Vec<String>, u32) {
+    let Elements { classes, enums, packages, notes } = elements;
+    let mut ret = Vec::new();
+    let mut width = CLASS_WIDTH;
+    let start_y = *y;
+    for r#enum in enums.values().filter(|r#enum| r#enum.package.as_deref() == package) {
+        let variants = get_variants(r#enum);
+        ids.classes.insert(r#enum.name.as_str(), format!("class-diag-{id}"));
+        let (a, y_tmp, variant_ids) = make_enum(id, unqualified_name(&r#enum.name), r#enum.doc.as_deref(), variants, Placement { parent, x: X_PADDING, y: *y });
         *y += y_tmp + Y_PADDING;
 
+        let members = ids.members.entry(r#enum.name.as_str()).or_default();
+        for (variant, variant_id) in r#enum.variants.iter().zip(variant_ids) {
+            members.entry(variant.name.as_str()).or_default().push(variant_id);
+        }
+
         ret.push(a)
     };
 
-    ret
+    for class in classes.values().filter(|class| class.package.as_deref() == package) {
+        let Class { doc, package: _, is_abstract, kind, name, parents: _, interfaces: _, attributes, methods } = class;
+        ids.classes.insert(name.as_str(), format!("class-diag-{id}"));
+        let description = ClassDescription {
+            name: unqualified_name(name),
+            kind: *kind,
+            is_abstract: *is_abstract,
+            doc: doc.as_deref(),
+            attributes: get_attributes(attributes),
+            methods: get_methods(methods)
+        };
+        let class_cells = make_class(id, description, Placement { parent, x: X_PADDING, y: *y });
+        *y += class_cells.height + Y_PADDING;
+
+        let members = ids.members.entry(name.as_str()).or_default();
+        let attributes = attributes.iter().map(|attribute| attribute.name.as_str()).zip(class_cells.attribute_ids);
+        let methods = methods.iter().map(|method| method.name.as_str()).zip(class_cells.method_ids);
+        for (member, member_id) in attributes.chain(methods) {
+            members.entry(member).or_default().push(member_id);
+        }
+
+        ret.push(class_cells.cells)
+    };
+
+    for inner in packages.iter().filter(|inner| inner.package.as_deref() == package) {
+        let package_id = format!("class-diag-{id}");
+        *id += 1;
+        let mut height = PACKAGE_START_HEIGHT;
+        let (content, content_width) = make_package_content(id, &mut height, Some(&inner.name), &package_id, elements, ids);
+        let package_width = content_width + 2 * X_PADDING;
+        ret.push(make_package(package_id, unqualified_name(&inner.name), parent, X_PADDING, *y, package_width, height).to_string());
+        ret.extend(content);
+        *y += height + Y_PADDING;
+        width = width.max(package_width);
+    }
+
+    let mut notes = notes.iter().filter(|note| note.package.as_deref() == package).peekable();
+    if notes.peek().is_some() {
+        let x = X_PADDING + width + X_PADDING;
+        let mut note_y = start_y;
+        for note in notes {
+            let note_id = format!("class-diag-{id}");
+            *id += 1;
+            let height = get_note_height(&note.text);
+            ret.push(make_note(note_id.clone(), &note.text, parent, x, note_y, height).to_string());
+            ids.notes.push((note, note_id));
+            note_y += height + Y_PADDING;
+        }
+        width += X_PADDING + NOTE_WIDTH;
+        *y = (*y).max(note_y);
+    }
+
+    (ret, width)
+}
+
+fn get_note_height(text: &str) -> u32 {
+    let lines: u32 = text.lines()
+        .map(|line| line.chars().count() as u32 / NOTE_LINE_LENGTH + 1)
+        .sum();
+
+    lines.max(1) * NOTE_LINE_HEIGHT + NOTE_LINE_HEIGHT
+}
+
+fn get_visibility_prefix(visibility: Option<Visibility>) -> &'static str {
+    match visibility {
+        Some(Visibility::Public) => "+ ",
+        Some(Visibility::Private) => "- ",
+        Some(Visibility::Protected) => "# ",
+        Some(Visibility::Package) => "~ ",
+        None => "",
+    }
+}
+
+fn get_font_style(is_static: bool, is_abstract: bool) -> u32 {
+    let mut font_style = 0;
+    if is_static {
+        font_style |= FONT_STYLE_UNDERLINE
+    }
+    if is_abstract {
+        font_style |= FONT_STYLE_ITALIC
+    }
+
+    font_style
 }
 
-fn get_attributes(attributes: &[Attribute]) -> Vec<String> {
+/// Label, font style and doc of each attribute
+fn get_attributes(attributes: &[Attribute]) -> Vec<MemberRow> {
     let mut v = Vec::new();
     for att in attributes {
-        v.push(match &att.r#type {
-            Some(r#type) => format!("{}: {}", att.name, r#type),
-            None => att.name.clone(),
-        })
+        let visibility = get_visibility_prefix(att.visibility);
+        let label = match &att.r#type {
+            Some(r#type) => format!("{visibility}{}: {}", att.name, r#type),
+            None => format!("{visibility}{}", att.name),
+        };
+        v.push((label, get_font_style(att.is_static, false), att.doc.clone()))
     };
 
     v
 }
 
-fn get_methods(attributes: &[Method]) -> Vec<String> {
+/// Label, font style and doc of each method
+fn get_methods(attributes: &[Method]) -> Vec<MemberRow> {
     let mut v = Vec::new();
     for att in attributes {
-        v.push(match &att.ret_type {
-            Some(ret_type) => format!("{}({}): {}", att.name, get_param_string(&att.parameters), ret_type),
-            None => format!("{}({})", att.name, get_param_string(&att.parameters)),
-        });
+        let visibility = get_visibility_prefix(att.visibility);
+        let label = match &att.ret_type {
+            Some(ret_type) => format!("{visibility}{}({}): {}", att.name, get_param_string(&att.parameters), ret_type),
+            None => format!("{visibility}{}({})", att.name, get_param_string(&att.parameters)),
+        };
+        v.push((label, get_font_style(att.is_static, att.is_abstract), att.doc.clone()));
+    };
+
+    v
+}
+
+/// Label and doc of each variant
+fn get_variants(r#enum: &Enum) -> Vec<(String, Option<String>)> {
+    let mut v = Vec::new();
+    for variant in &r#enum.variants {
+        let label = match &variant.payload {
+            VariantPayload::Unit => variant.name.clone(),
+            VariantPayload::Tuple(types) => format!("{}({})", variant.name, types.join(", ")),
+            VariantPayload::Struct(fields) => format!("{} {{ {} }}", variant.name, get_param_string(fields)),
+        };
+        v.push((label, variant.doc.clone()))
     };
 
     v
@@ -63,27 +353,31 @@ fn get_param_string(parameters: &[Attribute]) -> String {
         .map(|att| match &att.r#type {
                 Some(r#type) => format!("{}: {}", att.name, r#type),
                 None => att.name.clone(),
-        }).fold(String::new(), |l, r| {
-            if l.len() > 0 {
-                format!("{l}, {r}")
-            } else {
-                l
-            }
-        })
+        }).collect::<Vec<_>>()
+        .join(", ")
 }
 
-fn make_class(id: &mut u32, name: &str, attributes: Vec<String>, methods: Vec<String>, x_pos: u32, y_pos: u32) -> (String, u32) { // TODO: Calculate width
+fn make_class(id: &mut u32, description: ClassDescription, placement: Placement) -> ClassCells { // TODO: Calculate width
+    let ClassDescription { name, kind, is_abstract, doc, attributes, methods } = description;
     let mut class_v = Vec::new();
+    let mut attribute_ids = Vec::new();
+    let mut method_ids = Vec::new();
     let mut m_id = *id+1;
 
-    let mut y = START_HEIGHT;
+    let mut y = match kind {
+        ClassKind::Class => START_HEIGHT,
+        ClassKind::Interface => STEREOTYPE_START_HEIGHT,
+    };
 
-    for attr in attributes {
+    for (attr, font_style, attr_doc) in attributes {
+        attribute_ids.push(format!("class-diag-{m_id}"));
         let attrib = make_attribute(
             format!("class-diag-{m_id}"),
             attr,
+            font_style,
             format!("class-diag-{id}"),
-            y
+            y,
+            attr_doc.as_deref()
         );
         class_v.push(attrib);
 
@@ -98,12 +392,15 @@ fn make_class(id: &mut u32, name: &str, attributes: Vec<String>, methods: Vec<St
     m_id += 1;
     y += SEPARATOR_HEIGHT;
 
-    for method in methods {
+    for (method, font_style, method_doc) in methods {
+        method_ids.push(format!("class-diag-{m_id}"));
         class_v.push(make_method(
             format!("class-diag-{m_id}"),
             method,
+            font_style,
             format!("class-diag-{id}"),
-            y
+            y,
+            method_doc.as_deref()
         ));
 
         m_id += 1;
@@ -112,9 +409,10 @@ fn make_class(id: &mut u32, name: &str, attributes: Vec<String>, methods: Vec<St
     let class_title = make_class_title(
         format!("class-diag-{id}"),
         name,
-        "class-diag-1",
-        x_pos,
-        y_pos,
+        kind,
+        is_abstract,
+        doc,
+        placement,
         y
     );
 
@@ -124,7 +422,53 @@ fn make_class(id: &mut u32, name: &str, attributes: Vec<String>, methods: Vec<St
 
     *id = m_id;
 
-    (class, y)
+    ClassCells {
+        cells: class,
+        height: y,
+        attribute_ids,
+        method_ids
+    }
+}
+
+/// Returns the cells, the height of the enum and the ids of its variants
+fn make_enum(id: &mut u32, name: &str, doc: Option<&str>, variants: Vec<(String, Option<String>)>, placement: Placement) -> (String, u32, Vec<String>) {
+    let mut enum_v = Vec::new();
+    let mut variant_ids = Vec::new();
+    let mut m_id = *id+1;
+
+    let mut y = STEREOTYPE_START_HEIGHT;
+
+    for (variant, variant_doc) in variants {
+        variant_ids.push(format!("class-diag-{m_id}"));
+        enum_v.push(make_variant(
+            format!("class-diag-{m_id}"),
+            variant,
+            format!("class-diag-{id}"),
+            y,
+            variant_doc.as_deref()
+        ));
+
+        m_id += 1;
+        y += VARIANT_HEIGHT
+    }
+
+    let value = make_stereotype_title("enumeration", &escape_html(name));
+    let enum_title = make_swimlane(
+        format!("class-diag-{id}"),
+        value,
+        Style::default_stereotype_swimlane(),
+        doc,
+        placement,
+        y
+    );
+
+    let r#enum = enum_v.into_iter()
+        .map(|elem| elem.to_string())
+        .fold(enum_title.to_string(), |old_str, new_elem| old_str + &new_elem);
+
+    *id = m_id;
+
+    (r#enum, y, variant_ids)
 }
 
 fn make_separator_bar(id: String, parent: String, y: u32) -> Tag {
@@ -142,8 +486,19 @@ fn make_separator_bar(id: String, parent: String, y: u32) -> Tag {
     )
 }
 
-fn make_attribute(id: String, value: String, parent: String, y: u32) -> Tag {
-    let style = Style::default_text().to_string();
+fn make_member_style(font_style: u32) -> Style {
+    match font_style {
+        0 => Style::default_text(),
+        font_style => Style::text_with_font_style(font_style),
+    }
+}
+
+fn make_attribute(id: String, value: String, font_style: u32, parent: String, y: u32, doc: Option<&str>) -> Tag {
+    let value = escape_xml(&value);
+    let style = make_member_style(font_style).to_string();
+    if let Some(doc) = doc {
+        return make_documented_member(id, value, style, parent, y, ATTR_HEIGHT, doc)
+    }
     let vertex = "1";
     let width = CLASS_WIDTH;
     let height = ATTR_HEIGHT;
@@ -156,8 +511,12 @@ fn make_attribute(id: String, value: String, parent: String, y: u32) -> Tag {
     )
 }
 
-fn make_method(id: String, value: String, parent: String, y: u32) -> Tag {
-    let style = Style::default_text().to_string();
+fn make_method(id: String, value: String, font_style: u32, parent: String, y: u32, doc: Option<&str>) -> Tag {
+    let value = escape_xml(&value);
+    let style = make_member_style(font_style).to_string();
+    if let Some(doc) = doc {
+        return make_documented_member(id, value, style, parent, y, METHOD_HEIGHT, doc)
+    }
     let vertex = "1";
     let width = CLASS_WIDTH;
     let height = METHOD_HEIGHT;
@@ -170,10 +529,96 @@ fn make_method(id: String, value: String, parent: String, y: u32) -> Tag {
     )
 }
 
-fn make_class_title(id: String, value: &str, parent: &str, x: u32, y: u32, height: u32) -> Tag {
-    let style = Style::default_swimlane().to_string();
+fn make_variant(id: String, value: String, parent: String, y: u32, doc: Option<&str>) -> Tag {
+    let value = escape_xml(&value);
+    let style = Style::default_text().to_string();
+    if let Some(doc) = doc {
+        return make_documented_member(id, value, style, parent, y, VARIANT_HEIGHT, doc)
+    }
     let vertex = "1";
     let width = CLASS_WIDTH;
+    let height = VARIANT_HEIGHT;
+    let r#as = "geometry";
+
+    xml_tag!(
+        <mxCell id={id} value={value} style={style} vertex={vertex} parent={parent}>
+            <mxGeometry y={y} width={width} height={height} as={r#as}/>
+        </mxCell>
+    )
+}
+
+/// Members with a doc comment are wrapped in a `UserObject` to show it as a tooltip, the `UserObject` takes the id and label of the cell
+fn make_documented_member(id: String, value: String, style: String, parent: String, y: u32, height: u32, doc: &str) -> Tag {
+    let tooltip = escape_xml(doc);
+    let vertex = "1";
+    let width = CLASS_WIDTH;
+    let r#as = "geometry";
+
+    xml_tag!(
+        <UserObject id={id} label={value} tooltip={tooltip}>
+            <mxCell style={style} vertex={vertex} parent={parent}>
+                <mxGeometry y={y} width={width} height={height} as={r#as}/>
+            </mxCell>
+        </UserObject>
+    )
+}
+
+/// `name` must already be html escaped
+fn make_stereotype_title(stereotype: &str, name: &str) -> String {
+    escape_xml(&format!("«{stereotype}»<br><b>{name}</b>"))
+}
+
+fn make_class_title(id: String, name: &str, kind: ClassKind, is_abstract: bool, doc: Option<&str>, placement: Placement, height: u32) -> Tag {
+    let (value, style) = match (kind, is_abstract) {
+        (ClassKind::Class, false) => (escape_xml(name), Style::default_swimlane()),
+        (ClassKind::Class, true) => (
+            escape_xml(name),
+            Style::swimlane_with_font_style(FONT_STYLE_BOLD | FONT_STYLE_ITALIC)
+        ),
+        (ClassKind::Interface, false) => (
+            make_stereotype_title("interface", &escape_html(name)),
+            Style::default_stereotype_swimlane()
+        ),
+        (ClassKind::Interface, true) => (
+            make_stereotype_title("interface", &format!("<i>{}</i>", escape_html(name))),
+            Style::default_stereotype_swimlane()
+        ),
+    };
+
+    make_swimlane(id, value, style, doc, placement, height)
+}
+
+/// Like members, a documented swimlane is wrapped in a `UserObject`
+fn make_swimlane(id: String, value: String, style: Style, doc: Option<&str>, placement: Placement, height: u32) -> Tag {
+    let Placement { parent, x, y } = placement;
+    let style = style.to_string();
+    let vertex = "1";
+    let width = CLASS_WIDTH;
+    let r#as = "geometry";
+
+    match doc {
+        Some(doc) => {
+            let tooltip = escape_xml(doc);
+            xml_tag!(
+                <UserObject id={id} label={value} tooltip={tooltip}>
+                    <mxCell style={style} vertex={vertex} parent={parent}>
+                        <mxGeometry x={x} y={y} width={width} height={height} as={r#as}/>
+                    </mxCell>
+                </UserObject>
+            )
+        },
+        None => xml_tag!(
+            <mxCell id={id} value={value} style={style} vertex={vertex} parent={parent}>
+                <mxGeometry x={x} y={y} width={width} height={height} as={r#as}/>
+            </mxCell>
+        ),
+    }
+}
+
+fn make_package(id: String, name: &str, parent: &str, x: u32, y: u32, width: u32, height: u32) -> Tag {
+    let value = escape_xml(&escape_html(name));
+    let style = Style::default_package().to_string();
+    let vertex = "1";
     let r#as = "geometry";
 
     xml_tag!(
@@ -182,3 +627,54 @@ fn make_class_title(id: String, value: &str, parent: &str, x: u32, y: u32, heigh
         </mxCell>
     )
 }
+
+fn make_note(id: String, text: &str, parent: &str, x: u32, y: u32, height: u32) -> Tag {
+    let value = escape_xml(&escape_html(text).replace('\n', "<br>"));
+    let style = Style::default_note().to_string();
+    let vertex = "1";
+    let width = NOTE_WIDTH;
+    let r#as = "geometry";
+
+    xml_tag!(
+        <mxCell id={id} value={value} style={style} vertex={vertex} parent={parent}>
+            <mxGeometry x={x} y={y} width={width} height={height} as={r#as}/>
+        </mxCell>
+    )
+}
+
+fn make_edge(id: String, style: Style, parent: &str, source: &str, target: &str) -> Tag {
+    let value = "";
+    let style = style.to_string();
+    let edge = "1";
+    let width = "160";
+    let relative = "1";
+    let r#as = "geometry";
+
+    xml_tag!(
+        <mxCell id={id} value={value} style={style} edge={edge} parent={parent} source={source} target={target}>
+            <mxGeometry width={width} relative={relative} as={r#as}/>
+        </mxCell>
+    )
+}
+
+/// `x` is the position along the edge, -1 being the source end and 1 the target end
+fn make_edge_label(id: String, edge: &str, text: &str, x: f32, alignment: Alignment) -> Tag {
+    let value = escape_xml(&escape_html(text));
+    let style = match alignment {
+        Alignment::Center => Style::default_edge_label(),
+        alignment => Style::edge_label_with_alignment(alignment, VerticalAlignment::Bottom),
+    }.to_string();
+    let vertex = "1";
+    let connectable = "0";
+    let relative = "1";
+    let as1 = "geometry";
+    let as2 = "offset";
+
+    xml_tag!(
+        <mxCell id={id} value={value} style={style} parent={edge} vertex={vertex} connectable={connectable}>
+            <mxGeometry x={x} relative={relative} as={as1}>
+                <mxPoint as={as2}/>
+            </mxGeometry>
+        </mxCell>
+    )
+}
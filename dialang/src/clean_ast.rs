@@ -1,20 +1,45 @@
-use diaparser::Annotation;
+use diaparser::ClassKind;
+use diaparser::RelationshipKind;
+use diaparser::Visibility;
 use diaparser::Assignment;
 use diaparser::FuncCall;
-use diaparser::SequenceEntrypointBlock;
+use diaparser::Value as ParserValue;
+use diaparser::If;
+use diaparser::While;
+use diaparser::For;
 use diaparser::Expr as ParserExpr;
 use diaparser::Class as ParserClass;
-use diaparser::AnnotatedBlock as ParserAnnotatedBlock;
+use diaparser::Annotation as ParserAnnotation;
+use diaparser::AnnotationArg as ParserAnnotationArg;
+use diaparser::Implementation as ParserImplementation;
+use diaparser::Enum as ParserEnum;
+use diaparser::Relationship as ParserRelationship;
+use diaparser::Package as ParserPackage;
+use diaparser::Note as ParserNote;
+use diaparser::VariantPayload as ParserVariantPayload;
 use diaparser::TopLevelStatement as ParserTopLevelStatement;
 use diaparser::Method as ParserMethod;
 use diaparser::Attribute as ParserAttribute;
+use diaparser::Type as ParserType;
+use diaparser::Diagnostic;
+use diaparser::Label;
+use diaparser::Severity;
+use diaparser::Span;
 
 pub(crate) enum Statement {
     Assignment { name: String, expr: Vec<Statement> },
-    FuncCall { root: String, access: Option<String>, args: Vec<String> },
+    FuncCall { receiver: Option<String>, name: String, args: Vec<String> },
+    If { condition: String, then_branch: Vec<Statement>, else_branch: Vec<Statement> },
+    While { condition: String, body: Vec<Statement> },
+    For { variable: String, iterable: String, body: Vec<Statement> },
+    Return { value: Option<String> },
 }
 
 pub(crate) struct Method {
+    pub(crate) doc: Option<String>,
+    pub(crate) visibility: Option<Visibility>,
+    pub(crate) is_static: bool,
+    pub(crate) is_abstract: bool,
     pub(crate) name: String,
     pub(crate) parameters: Vec<Attribute>,
     pub(crate) ret_type: Option<String>,
@@ -22,102 +47,485 @@ pub(crate) struct Method {
 }
 
 pub(crate) struct Class {
+    pub(crate) doc: Option<String>,
+    pub(crate) package: Option<String>,
+    pub(crate) is_abstract: bool,
+    pub(crate) kind: ClassKind,
     pub(crate) name: String,
+    pub(crate) parents: Vec<String>,
+    pub(crate) interfaces: Vec<String>,
     pub(crate) attributes: Vec<Attribute>,
     pub(crate) methods: Vec<Method>
 }
 
+/// Annotations known to dialang, any other name is reported and ignored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Annotation {
+    /// `@SequenceEntrypoint` or `@SequenceEntrypoint("Name")`, only on calls
+    SequenceEntrypoint,
+}
+
+impl Annotation {
+    fn from_name(name: &str) -> Option<Annotation> {
+        match name {
+            "SequenceEntrypoint" => Some(Annotation::SequenceEntrypoint),
+            _ => None
+        }
+    }
+}
+
 pub(crate) struct AnnotatedBlock {
     pub(crate) annotation: Annotation,
-    pub(crate) elements: Vec<ParserExpr>
+    pub(crate) name: Option<String>,
+    pub(crate) elements: Vec<Statement>
+}
+
+pub(crate) struct Implementation {
+    pub(crate) package: Option<String>,
+    pub(crate) interface: String,
+    pub(crate) implementor: String,
+    pub(crate) methods: Vec<Method>
+}
+
+pub(crate) enum VariantPayload {
+    Unit,
+    Tuple(Vec<String>),
+    Struct(Vec<Attribute>)
+}
+
+pub(crate) struct Variant {
+    pub(crate) doc: Option<String>,
+    pub(crate) name: String,
+    pub(crate) payload: VariantPayload
+}
+
+pub(crate) struct Enum {
+    pub(crate) doc: Option<String>,
+    pub(crate) package: Option<String>,
+    pub(crate) name: String,
+    pub(crate) variants: Vec<Variant>
 }
 
 pub(crate) enum TopLevelStatement {
     Class(Class),
-    AnnotatedBlock(AnnotatedBlock)
+    AnnotatedBlock(AnnotatedBlock),
+    Implementation(Implementation),
+    Enum(Enum),
+    Relationship(Relationship),
+    Package(Package),
+    Note(Note)
+}
+
+/// Names of packages and of their content are qualified, `outer::inner::Name`
+pub(crate) struct Package {
+    pub(crate) package: Option<String>,
+    pub(crate) name: String
+}
+
+pub(crate) struct Note {
+    pub(crate) package: Option<String>,
+    pub(crate) target: Option<NoteTarget>,
+    pub(crate) text: String
+}
+
+pub(crate) struct NoteTarget {
+    pub(crate) class: String,
+    pub(crate) member: Option<String>
+}
+
+pub(crate) struct Relationship {
+    pub(crate) package: Option<String>,
+    pub(crate) source: String,
+    pub(crate) source_label: Option<String>,
+    pub(crate) kind: RelationshipKind,
+    pub(crate) target_label: Option<String>,
+    pub(crate) target: String,
+    pub(crate) name: Option<String>
 }
 
 pub(crate) struct Attribute {
+    pub(crate) doc: Option<String>,
+    pub(crate) visibility: Option<Visibility>,
+    pub(crate) is_static: bool,
     pub(crate) name: String,
     pub(crate) r#type: Option<String>
 }
 
-pub(crate) fn clean_parser_top_lvl_statement(stmnt: ParserTopLevelStatement) -> TopLevelStatement {
+/// Packages are flattened, the returned package is followed by its content.
+/// Problems that don't stop the statement from being drawn are pushed to `diagnostics` as warnings
+pub(crate) fn clean_parser_top_lvl_statement(stmnt: ParserTopLevelStatement, package: Option<&str>, diagnostics: &mut Vec<Diagnostic>) -> Vec<TopLevelStatement> {
     match stmnt {
-        ParserTopLevelStatement::Class(class) => TopLevelStatement::Class(clean_parser_class(class)),
-        ParserTopLevelStatement::AnnotatedBlock(ablock) => TopLevelStatement::AnnotatedBlock(clean_parser_annotated_block(ablock)),
+        ParserTopLevelStatement::Class(class) => vec![TopLevelStatement::Class(clean_parser_class(class, package, diagnostics))],
+        ParserTopLevelStatement::Implementation(implementation) => vec![TopLevelStatement::Implementation(clean_parser_implementation(implementation, package, diagnostics))],
+        ParserTopLevelStatement::Enum(r#enum) => vec![TopLevelStatement::Enum(clean_parser_enum(r#enum, package, diagnostics))],
+        ParserTopLevelStatement::Relationship(relationship) => vec![TopLevelStatement::Relationship(clean_parser_relationship(relationship, package))],
+        ParserTopLevelStatement::Package(inner) => clean_parser_package(inner, package, diagnostics),
+        ParserTopLevelStatement::Note(note) => vec![TopLevelStatement::Note(clean_parser_note(note, package))],
+        // Imports are followed while reading the files
+        ParserTopLevelStatement::Import(_) => vec![],
+        ParserTopLevelStatement::Annotated(annotations, statement) => match statement.0 {
+            ParserTopLevelStatement::Call(function) => {
+                let mut annotations = clean_annotations(annotations, &[Annotation::SequenceEntrypoint], diagnostics).into_iter();
+                match annotations.next() {
+                    Some((annotation, used, args)) => {
+                        for (repeated, span, _) in annotations {
+                            let mut diagnostic = warning(
+                                span,
+                                format!("@{:?} is repeated on the call to {}, only the first one is used", repeated, function.name.0),
+                                "ignored"
+                            );
+                            diagnostic.labels.push(Label { span: used.clone(), message: "this one is used".to_string() });
+                            diagnostics.push(diagnostic);
+                        }
+                        vec![TopLevelStatement::AnnotatedBlock(clean_parser_annotated_block(annotation, args, function, diagnostics))]
+                    },
+                    None => vec![],
+                }
+            },
+            statement => {
+                clean_annotations(annotations, &[], diagnostics);
+                clean_parser_top_lvl_statement(statement, package, diagnostics)
+            },
+        },
+        ParserTopLevelStatement::Call(function) => {
+            diagnostics.push(warning(
+                function.name.1,
+                format!("Ignoring call to {}, calls outside of methods need an annotation such as @SequenceEntrypoint", function.name.0),
+                "ignored"
+            ));
+            vec![]
+        },
+    }
+}
+
+/// A problem that doesn't stop the element from being drawn, `label` says what happens at `span`
+fn warning(span: Span, message: String, label: &str) -> Diagnostic {
+    Diagnostic {
+        span: span.clone(),
+        severity: Severity::Warning,
+        code: None,
+        message,
+        labels: vec![Label { span, message: label.to_string() }],
+        notes: Vec::new()
+    }
+}
+
+/// A known annotation with its span and arguments
+type AllowedAnnotation = (Annotation, Span, Vec<(ParserAnnotationArg, Span)>);
+
+/// Keeps the known annotations allowed on the annotated element, the others are reported and ignored
+fn clean_annotations(annotations: Vec<(ParserAnnotation, Span)>, allowed: &[Annotation], diagnostics: &mut Vec<Diagnostic>) -> Vec<AllowedAnnotation> {
+    annotations.into_iter()
+        .filter_map(|(ParserAnnotation { name: (name, _), args }, span)| match Annotation::from_name(&name) {
+            Some(annotation) if allowed.contains(&annotation) => Some((annotation, span, args)),
+            Some(_) => {
+                diagnostics.push(warning(span, format!("@{name} cannot be used here, it is ignored"), "not allowed here"));
+                None
+            },
+            None => {
+                diagnostics.push(warning(span, format!("Unknown annotation @{name}, it is ignored"), "unknown annotation"));
+                None
+            },
+        })
+        .collect()
+}
+
+fn clean_parser_package(inner: ParserPackage, package: Option<&str>, diagnostics: &mut Vec<Diagnostic>) -> Vec<TopLevelStatement> {
+    let ParserPackage {
+        name: (name, _),
+        statements
+    } = inner;
+
+    let name = qualify_name(package, &name);
+    let mut v = Vec::new();
+    for (stmnt, _) in statements {
+        v.extend(clean_parser_top_lvl_statement(stmnt, Some(&name), diagnostics))
+    }
+    v.insert(0, TopLevelStatement::Package(Package {
+        package: package.map(str::to_string),
+        name
+    }));
+
+    v
+}
+
+fn qualify_name(package: Option<&str>, name: &str) -> String {
+    match package {
+        Some(package) => format!("{package}::{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Finds what `name` refers to when used inside `package`, looking in the innermost package first
+pub(crate) fn resolve_name(package: Option<&str>, name: &str, is_defined: impl Fn(&str) -> bool) -> Option<String> {
+    let mut package = package;
+    while let Some(scope) = package {
+        let qualified = format!("{scope}::{name}");
+        if is_defined(&qualified) {
+            return Some(qualified)
+        }
+        package = scope.rsplit_once("::").map(|(outer, _)| outer);
+    }
+
+    if is_defined(name) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+/// Name without the package path
+pub(crate) fn unqualified_name(name: &str) -> &str {
+    match name.rsplit_once("::") {
+        Some((_, name)) => name,
+        None => name,
+    }
+}
+
+fn clean_parser_note(note: ParserNote, package: Option<&str>) -> Note {
+    let ParserNote {
+        target,
+        text: (text, _)
+    } = note;
+
+    let target = target.map(|(target, _)| NoteTarget {
+        class: target.class.0,
+        member: target.member.map(|(member, _)| member)
+    });
+
+    Note {
+        package: package.map(str::to_string),
+        target,
+        text
+    }
+}
+
+fn clean_parser_relationship(relationship: ParserRelationship, package: Option<&str>) -> Relationship {
+    let ParserRelationship {
+        source: (source, _),
+        source_label,
+        kind: (kind, _),
+        target_label,
+        target: (target, _),
+        name
+    } = relationship;
+
+    Relationship {
+        package: package.map(str::to_string),
+        source,
+        source_label: source_label.map(|(label, _)| label),
+        kind,
+        target_label: target_label.map(|(label, _)| label),
+        target,
+        name: name.map(|(name, _)| name)
+    }
+}
+
+fn clean_parser_enum(r#enum: ParserEnum, package: Option<&str>, diagnostics: &mut Vec<Diagnostic>) -> Enum {
+    let ParserEnum {
+        doc,
+        name: (name, _),
+        variants
+    } = r#enum;
+
+    let variants = variants.into_iter().map(|(variant, _)| {
+        clean_annotations(variant.annotations, &[], diagnostics);
+        let payload = match variant.payload {
+            ParserVariantPayload::Unit => VariantPayload::Unit,
+            ParserVariantPayload::Tuple(types) => VariantPayload::Tuple(
+                types.into_iter().map(|(ty, _)| clean_parser_type(ty)).collect()
+            ),
+            ParserVariantPayload::Struct(fields) => VariantPayload::Struct(
+                fields.into_iter().map(|(field, _)| clean_parser_attribute(field, diagnostics)).collect()
+            ),
+        };
+
+        Variant {
+            doc: variant.doc.map(|(doc, _)| doc),
+            name: variant.name.0,
+            payload
+        }
+    }).collect();
+
+    Enum {
+        doc: doc.map(|(doc, _)| doc),
+        name: qualify_name(package, &name),
+        package: package.map(str::to_string),
+        variants
+    }
+}
+
+fn clean_parser_implementation(implementation: ParserImplementation, package: Option<&str>, diagnostics: &mut Vec<Diagnostic>) -> Implementation {
+    let ParserImplementation {
+        interface: (interface, _),
+        implementor: (implementor, _),
+        methods
+    } = implementation;
+
+    let methods = methods.into_iter().map(|(method, _)| {
+        clean_parser_method(method, diagnostics)
+    }).collect();
+
+    Implementation {
+        package: package.map(str::to_string),
+        interface,
+        implementor,
+        methods
     }
 }
 
-pub(crate) fn clean_parser_annotated_block(ablock: ParserAnnotatedBlock) -> AnnotatedBlock {
-    let (annotation, elements) = match ablock {
-        ParserAnnotatedBlock::SequenceEntrypoint(SequenceEntrypointBlock {
-            function: (function, _)
-        }) => {
-            (
-                Annotation::SequenceEntrypoint,
-                vec![ParserExpr::FuncCall(Box::new(function))]
-            )
+fn clean_parser_annotated_block(annotation: Annotation, args: Vec<(ParserAnnotationArg, Span)>, function: FuncCall, diagnostics: &mut Vec<Diagnostic>) -> AnnotatedBlock {
+    let mut name = None;
+    match annotation {
+        Annotation::SequenceEntrypoint => for (arg, span) in args {
+            match (arg.key, arg.value.0) {
+                (None, ParserValue::Str(value)) if name.is_none() => name = Some(value),
+                (Some((key, _)), ParserValue::Str(value)) if key == "name" && name.is_none() => name = Some(value),
+                (key, value) => diagnostics.push(warning(
+                    span,
+                    format!(
+                        "Unexpected argument {}{} for @SequenceEntrypoint, expected the diagram name as a string",
+                        key.map(|(key, _)| format!("{key} = ")).unwrap_or_default(),
+                        value_to_string(&value)
+                    ),
+                    "ignored"
+                )),
+            }
         },
-    };
+    }
 
     AnnotatedBlock {
         annotation,
-        elements
+        name,
+        elements: parserexpr_to_statement(ParserExpr::FuncCall(Box::new(function)))
     }
 }
 
-fn clean_parser_class(class: ParserClass) -> Class {
+fn clean_parser_class(class: ParserClass, package: Option<&str>, diagnostics: &mut Vec<Diagnostic>) -> Class {
     let ParserClass {
+        doc,
+        is_abstract,
+        kind,
         name: (name, _),
+        parents,
+        interfaces,
         attributes,
         methods
     } = class;
+    let parents = parents.into_iter().map(|(parent, _)| parent).collect();
+    let interfaces = interfaces.into_iter().map(|(interface, _)| interface).collect();
+
     let attributes = attributes.into_iter().map(|(attr, _)| {
-        clean_parser_attribute(attr)
+        clean_parser_attribute(attr, diagnostics)
     }).collect();
 
     let methods = methods.into_iter().map(|(method, _)| {
-        clean_parser_method(method)
+        clean_parser_method(method, diagnostics)
     }).collect();
 
     Class {
-        name,
+        doc: doc.map(|(doc, _)| doc),
+        package: package.map(str::to_string),
+        is_abstract,
+        kind,
+        name: qualify_name(package, &name),
+        parents,
+        interfaces,
         attributes,
         methods
     }
 }
 
-fn clean_parser_attribute(attribute: ParserAttribute) -> Attribute {
+fn clean_parser_attribute(attribute: ParserAttribute, diagnostics: &mut Vec<Diagnostic>) -> Attribute {
     let ParserAttribute {
+        doc,
+        annotations,
+        visibility,
+        is_static,
         name: (name, _),
         r#type,
     } = attribute;
 
+    clean_annotations(annotations, &[], diagnostics);
+
+    let visibility = visibility.map(|(visibility, _)| visibility);
+
     let r#type = match r#type {
-        Some((ty, _)) => Some(ty),
+        Some((ty, _)) => Some(clean_parser_type(ty)),
         None => None,
     };
 
     Attribute {
+        doc: doc.map(|(doc, _)| doc),
+        visibility,
+        is_static,
         name,
         r#type
     }
 }
 
-fn clean_parser_method(method: ParserMethod) -> Method {
+fn clean_parser_type(r#type: ParserType) -> String {
+    match r#type {
+        ParserType::Path { name: (name, _), generics } => {
+            if generics.is_empty() {
+                name
+            } else {
+                let generics = generics.into_iter()
+                    .map(|(generic, _)| clean_parser_type(generic))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{name}<{generics}>")
+            }
+        },
+        ParserType::Reference { mutable, inner } => {
+            let inner = clean_parser_type(inner.0);
+            if mutable {
+                format!("&mut {inner}")
+            } else {
+                format!("&{inner}")
+            }
+        },
+        ParserType::Array { inner, len } => {
+            let inner = clean_parser_type(inner.0);
+            match len {
+                Some((len, _)) => format!("[{inner}; {len}]"),
+                None => format!("[{inner}]"),
+            }
+        },
+        ParserType::Tuple(types) => {
+            let types = types.into_iter()
+                .map(|(ty, _)| clean_parser_type(ty))
+                .collect::<Vec<_>>();
+            match types.len() {
+                1 => format!("({},)", types[0]),
+                _ => format!("({})", types.join(", ")),
+            }
+        },
+    }
+}
+
+fn clean_parser_method(method: ParserMethod, diagnostics: &mut Vec<Diagnostic>) -> Method {
     let ParserMethod {
+        doc,
+        annotations,
+        visibility,
+        is_static,
+        is_abstract,
         name: (name, _),
         parameters,
         ret_type,
         body,
     } = method;
 
+    clean_annotations(annotations, &[], diagnostics);
+
+    let visibility = visibility.map(|(visibility, _)| visibility);
+
     let parameters = parameters.into_iter()
-        .map(|(attr, _)| clean_parser_attribute(attr)).collect();
+        .map(|(attr, _)| clean_parser_attribute(attr, diagnostics)).collect();
 
     let ret_type = match ret_type {
-        Some((ty, _)) => Some(ty),
+        Some((ty, _)) => Some(clean_parser_type(ty)),
         None => None,
     };
 
@@ -127,6 +535,10 @@ fn clean_parser_method(method: ParserMethod) -> Method {
     };
 
     Method {
+        doc: doc.map(|(doc, _)| doc),
+        visibility,
+        is_static,
+        is_abstract,
         name,
         parameters,
         ret_type,
@@ -137,17 +549,9 @@ fn clean_parser_method(method: ParserMethod) -> Method {
 fn parserexpr_to_statement(expr: ParserExpr) -> Vec<Statement> {
     match expr {
         ParserExpr::FuncCall(f) => {
-            let FuncCall{
-                root: (root, _),
-                access,
-                args
-            } = *f;
-
-            vec![Statement::FuncCall {
-                root,
-                access: access.map(|(access, _)| access),
-                args: args.into_iter().map(|(arg, _)| arg).collect()
-            }]
+            let mut v = Vec::new();
+            collect_calls(&ParserValue::Call(f), &mut v);
+            v
         },
         ParserExpr::Assignment(a) =>{
             let Assignment {
@@ -168,6 +572,120 @@ fn parserexpr_to_statement(expr: ParserExpr) -> Vec<Statement> {
             }
             v
         },
+        ParserExpr::If(i) => {
+            let If {
+                condition: (condition, _),
+                then_branch: (then_branch, _),
+                else_branch
+            } = *i;
+
+            let mut v = Vec::new();
+            collect_calls(&condition, &mut v);
+            v.push(Statement::If {
+                condition: value_to_string(&condition),
+                then_branch: parserexpr_to_statement(then_branch),
+                else_branch: match else_branch {
+                    Some((else_branch, _)) => parserexpr_to_statement(else_branch),
+                    None => vec![],
+                }
+            });
+            v
+        },
+        ParserExpr::While(w) => {
+            let While {
+                condition: (condition, _),
+                body: (body, _)
+            } = *w;
+
+            let mut v = Vec::new();
+            collect_calls(&condition, &mut v);
+            v.push(Statement::While {
+                condition: value_to_string(&condition),
+                body: parserexpr_to_statement(body)
+            });
+            v
+        },
+        ParserExpr::For(f) => {
+            let For {
+                variable: (variable, _),
+                iterable: (iterable, _),
+                body: (body, _)
+            } = *f;
+
+            let mut v = Vec::new();
+            collect_calls(&iterable, &mut v);
+            v.push(Statement::For {
+                variable,
+                iterable: value_to_string(&iterable),
+                body: parserexpr_to_statement(body)
+            });
+            v
+        },
+        ParserExpr::Return(value) => {
+            let mut v = Vec::new();
+            if let Some((value, _)) = &value {
+                collect_calls(value, &mut v);
+            }
+            v.push(Statement::Return {
+                value: value.map(|(value, _)| value_to_string(&value))
+            });
+            v
+        },
         ParserExpr::Error => vec![],
     }
 }
+
+/// Flattens the calls made while evaluating `value` in the order they happen,
+/// a call comes after the calls of its receiver and of its arguments
+fn collect_calls(value: &ParserValue, calls: &mut Vec<Statement>) {
+    match value {
+        ParserValue::Call(f) => {
+            let FuncCall {
+                receiver,
+                name: (name, _),
+                args
+            } = f.as_ref();
+
+            if let Some((receiver, _)) = receiver {
+                collect_calls(receiver, calls);
+            }
+            for (arg, _) in args {
+                collect_calls(arg, calls);
+            }
+
+            calls.push(Statement::FuncCall {
+                receiver: receiver.as_ref().map(|(receiver, _)| value_to_string(receiver)),
+                name: name.clone(),
+                args: args.iter().map(|(arg, _)| value_to_string(arg)).collect()
+            })
+        },
+        ParserValue::Member(member) => collect_calls(&member.object.0, calls),
+        ParserValue::Ident(_)
+        | ParserValue::Str(_)
+        | ParserValue::Int(_)
+        | ParserValue::Float(_)
+        | ParserValue::Bool(_) => (),
+    }
+}
+
+/// Values are kept as written in the source, they are used as message arguments,
+/// guards of sequence diagram fragments and to label returns
+fn value_to_string(value: &ParserValue) -> String {
+    match value {
+        ParserValue::Ident(name) => name.clone(),
+        ParserValue::Str(s) => format!("\"{s}\""),
+        ParserValue::Int(n) | ParserValue::Float(n) => n.clone(),
+        ParserValue::Bool(b) => b.to_string(),
+        ParserValue::Member(member) => format!("{}.{}", value_to_string(&member.object.0), member.member.0),
+        ParserValue::Call(f) => {
+            let args = f.args.iter()
+                .map(|(arg, _)| value_to_string(arg))
+                .collect::<Vec<_>>()
+                .join(", ");
+            match &f.receiver {
+                Some((receiver, _)) => format!("{}.{}({args})", value_to_string(receiver), f.name.0),
+                None => format!("{}({args})", f.name.0),
+            }
+        },
+    }
+}
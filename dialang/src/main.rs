@@ -2,17 +2,21 @@ use std::{process::exit, collections::HashMap};
 
 use options::Options;
 
-use diaparser::Annotation;
-
 use crate::class_diag::make_class_diag;
-use crate::clean_ast::{clean_parser_top_lvl_statement, TopLevelStatement};
+use crate::clean_ast::{clean_parser_top_lvl_statement, resolve_name, Annotation, TopLevelStatement};
 use crate::seq_diag::make_seq_diag;
+use crate::sources::Sources;
+use crate::xml::escape_xml;
 
 mod seq_diag;
 mod class_diag;
 mod options;
 mod style;
 mod clean_ast;
+mod xml;
+mod sources;
+mod source_map;
+mod report;
 
 fn main() {
     let opt = Options::load();
@@ -21,47 +25,98 @@ fn main() {
     }
 
     println!("Reading from {:?}", opt.input_path);
-    let input = std::fs::read_to_string(opt.input_path).unwrap();
-    let (tokens, _) = diaparser::tokenize(&input);
-
-    let tokens = match tokens {
-        Ok(t) => t,
-        Err(_) => panic!("Error while parsing the file, check that the syntax is correct.\nThis message is temporary, there will be better ones in the future."),
+    let mut sources = Sources::default();
+    let tokens = match sources.parse_with_imports(&opt.input_path) {
+        Some(tokens) => tokens,
+        None => exit(1),
     };
+    // Cleaning can add warnings, they are reported with the parser diagnostics
+    let statements = tokens.into_iter()
+        .flat_map(|(stmnt, _)| clean_parser_top_lvl_statement(stmnt, None, &mut sources.diagnostics))
+        .collect::<Vec<_>>();
+    let has_errors = sources.report_diagnostics(opt.color);
+    if has_errors && opt.strict {
+        exit(1)
+    }
 
     let mut classes = HashMap::new();
-    let mut annotated_blocks = HashMap::new();
+    let mut enums = HashMap::new();
+    let mut annotated_blocks = Vec::new();
+    let mut implementations = Vec::new();
+    let mut relationships = Vec::new();
+    let mut packages = Vec::new();
+    let mut notes = Vec::new();
 
-    tokens.into_iter()
-        .map(|(class, _)| clean_parser_top_lvl_statement(class))
+    statements.into_iter()
         .for_each(|stmnt| {
             match stmnt {
                 TopLevelStatement::Class(class) => {
                     classes.insert(class.name.clone(), class);
                 },
                 TopLevelStatement::AnnotatedBlock(ablock) => {
-                    annotated_blocks.insert(ablock.annotation, ablock.elements);
+                    annotated_blocks.push(ablock);
+                },
+                TopLevelStatement::Implementation(implementation) => {
+                    implementations.push(implementation);
+                },
+                TopLevelStatement::Enum(r#enum) => {
+                    enums.insert(r#enum.name.clone(), r#enum);
+                },
+                TopLevelStatement::Relationship(relationship) => {
+                    relationships.push(relationship);
+                },
+                TopLevelStatement::Package(package) => {
+                    packages.push(package);
+                },
+                TopLevelStatement::Note(note) => {
+                    notes.push(note);
                 },
             }
         });
 
+    for implementation in implementations {
+        let package = implementation.package.as_deref();
+        let implementor = resolve_name(package, &implementation.implementor, |name| classes.contains_key(name));
+        let interface = resolve_name(package, &implementation.interface, |name| classes.contains_key(name))
+            .unwrap_or(implementation.interface.clone());
+        match implementor.and_then(|implementor| classes.get_mut(&implementor)) {
+            Some(class) => {
+                class.interfaces.push(interface);
+                class.methods.extend(implementation.methods);
+            },
+            None => eprintln!("Cannot implement {} for {}, {} is not defined", implementation.interface, implementation.implementor, implementation.implementor),
+        }
+    }
+
     let class_diag = if opt.class_diag {
-        let classes = make_class_diag(&classes);
+        let classes = make_class_diag(&classes, &enums, &packages, &relationships, &notes);
         classes.into_iter()
             .collect::<String>()
     } else {
         "".to_string()
     };
 
-    let seq_diag = if opt.seq_diag {
-        if let Some(entry_points) = annotated_blocks.get(&Annotation::SequenceEntrypoint) {
-            dbg!(entry_points);
-            make_seq_diag(&classes).into_iter()
-                .collect()
-        } else {
+    let seq_diags = if opt.seq_diag {
+        let entry_points = annotated_blocks.iter()
+            .filter(|ablock| ablock.annotation == Annotation::SequenceEntrypoint)
+            .collect::<Vec<_>>();
+        if entry_points.is_empty() {
             eprintln!("Cannot create a seqence diagram without @SequenceEntrypoint");
-            "".to_string()
         }
+        entry_points.iter()
+            .enumerate()
+            .map(|(i, entry_point)| {
+                let id = format!("seq-diag-{}", i+1);
+                let name = match (&entry_point.name, entry_points.len()) {
+                    (Some(name), _) => escape_xml(name),
+                    (None, 1) => "Sequence Diagram".to_string(),
+                    (None, _) => format!("Sequence Diagram {}", i+1),
+                };
+                let seq_diag = make_seq_diag(&id, &entry_point.elements, &classes).into_iter()
+                    .collect::<String>();
+                format!(include_str!("../../templates/seq_diag.xml"), id=id, name=name, seq_diag=seq_diag)
+            })
+            .collect()
     } else {
         "".to_string()
     };
@@ -72,7 +127,10 @@ fn main() {
         "".to_string()
     };
 
-    let file = format!(include_str!("../../templates/doc.xml"), seq_diag=seq_diag, comm_diag=comm_diag, class_diag=class_diag);
+    let file = format!(include_str!("../../templates/doc.xml"), seq_diags=seq_diags, comm_diag=comm_diag, class_diag=class_diag);
     println!("Results written to {:?}", opt.output_path);
     std::fs::write(opt.output_path, file).unwrap();
+    if has_errors {
+        eprintln!("The diagrams are missing the statements with errors, use --strict to write nothing instead")
+    }
 }
@@ -1,4 +1,4 @@
-use std::{env, path::PathBuf, vec::IntoIter};
+use std::{env, io::IsTerminal, path::PathBuf, vec::IntoIter};
 
 type ArgIter = IntoIter<String>;
 
@@ -7,6 +7,10 @@ pub(crate) struct Options {
     pub(crate) class_diag: bool,
     pub(crate) comm_diag: bool,
     pub(crate) seq_diag: bool,
+    /// Whether diagnostics are colored, by default only when printed to a terminal
+    pub(crate) color: bool,
+    /// Don't write the diagrams if there are errors, instead of leaving out the statements with errors
+    pub(crate) strict: bool,
     pub(crate) input_path: PathBuf,
     pub(crate) output_path: PathBuf
 }
@@ -18,6 +22,8 @@ impl Default for Options {
             eval: true,
             comm_diag: false,
             seq_diag: false,
+            color: std::io::stderr().is_terminal(),
+            strict: false,
             input_path: PathBuf::from("input.txt"),
             output_path: PathBuf::from("output.drawio"),
         }
@@ -41,6 +47,9 @@ impl Options {
                 "--class" => options.class(),
                 "--comm" => options.comm(),
                 "--seq" => options.seq(),
+                "--color" => options.color = true,
+                "--no-color" => options.color = false,
+                "--strict" => options.strict = true,
                 "--input" => options.input(&mut iter),
                 "--output" => options.output(&mut iter),
                 a => options.handle_arg(a, &mut iter)
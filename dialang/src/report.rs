@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use diaparser::{Diagnostic, FileId, Severity, Span};
+
+use crate::sources::SourceFile;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders diagnostics like rustc does, with the lines of their spans underlined
+pub(crate) struct Renderer<'a> {
+    files: &'a [SourceFile],
+    color: bool
+}
+
+/// An underlined part of a line, columns are counted in characters
+struct Mark<'a> {
+    line: usize,
+    start: usize,
+    end: usize,
+    primary: bool,
+    message: Option<&'a str>
+}
+
+impl<'a> Renderer<'a> {
+    pub(crate) fn new(files: &'a [SourceFile], color: bool) -> Renderer<'a> {
+        Renderer {
+            files,
+            color
+        }
+    }
+
+    fn paint(&self, text: &str, style: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn make_mark<'b>(&self, span: &Span, primary: bool, message: Option<&'b str>) -> Mark<'b> {
+        let file = &self.files[span.file];
+        let (line, start) = file.source_map.line_col(&file.content, span.range.start);
+        let (end_line, end) = file.source_map.line_col(&file.content, span.range.end);
+        // Spans over multiple lines are underlined until the end of their first line
+        let end = match end_line == line {
+            true => end,
+            false => file.source_map.line_text(&file.content, line).chars().count(),
+        };
+
+        Mark {
+            line,
+            start,
+            end: end.max(start + 1),
+            primary,
+            message
+        }
+    }
+
+    pub(crate) fn render(&self, diagnostic: &Diagnostic) -> String {
+        let severity_style = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+
+        // The file of the main span comes first
+        let primary_label = diagnostic.labels.iter().find(|label| label.span == diagnostic.span);
+        let mut files: Vec<(FileId, Vec<Mark>)> = vec![(
+            diagnostic.span.file,
+            vec![self.make_mark(&diagnostic.span, true, primary_label.map(|label| label.message.as_str()))]
+        )];
+        for label in diagnostic.labels.iter().filter(|label| label.span != diagnostic.span) {
+            let mark = self.make_mark(&label.span, false, Some(&label.message));
+            match files.iter_mut().find(|(file, _)| *file == label.span.file) {
+                Some((_, marks)) => marks.push(mark),
+                None => files.push((label.span.file, vec![mark])),
+            }
+        }
+
+        let gutter_width = files.iter()
+            .flat_map(|(_, marks)| marks.iter())
+            .map(|mark| (mark.line + 1).to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty_gutter = format!("{:gutter_width$} {}", "", self.paint("|", BLUE));
+
+        let mut out = String::new();
+        let severity = match diagnostic.code {
+            Some(code) => format!("{}[{code}]", diagnostic.severity),
+            None => diagnostic.severity.to_string(),
+        };
+        writeln!(out, "{}{}", self.paint(&severity, severity_style), self.paint(&format!(": {}", diagnostic.message), BOLD)).unwrap();
+        for (i, (file, mut marks)) in files.into_iter().enumerate() {
+            let source = &self.files[file];
+            let arrow = match i {
+                0 => "-->",
+                _ => ":::",
+            };
+            writeln!(out, "{:gutter_width$}{} {}:{}:{}", "", self.paint(arrow, BLUE), source.path.display(), marks[0].line + 1, marks[0].start + 1).unwrap();
+            writeln!(out, "{empty_gutter}").unwrap();
+
+            marks.sort_by_key(|mark| (mark.line, mark.start));
+            let mut last_line = None;
+            for mark in &marks {
+                if last_line != Some(mark.line) {
+                    if last_line.is_some_and(|last_line| mark.line > last_line + 1) {
+                        writeln!(out, "{}", self.paint("...", BLUE)).unwrap();
+                    }
+                    let line_number = format!("{:>gutter_width$}", mark.line + 1);
+                    writeln!(out, "{} {} {}", self.paint(&line_number, BLUE), self.paint("|", BLUE), source.source_map.line_text(&source.content, mark.line)).unwrap();
+                    last_line = Some(mark.line);
+                }
+
+                let (underline, style) = match mark.primary {
+                    true => ("^", severity_style),
+                    false => ("-", BLUE),
+                };
+                let underline = match mark.message {
+                    Some(message) => format!("{} {message}", underline.repeat(mark.end - mark.start)),
+                    None => underline.repeat(mark.end - mark.start),
+                };
+                writeln!(out, "{empty_gutter} {}{}", " ".repeat(mark.start), self.paint(&underline, style)).unwrap();
+            }
+        }
+
+        if !diagnostic.notes.is_empty() {
+            writeln!(out, "{empty_gutter}").unwrap();
+            for note in &diagnostic.notes {
+                writeln!(out, "{:gutter_width$} {} {}: {note}", "", self.paint("=", BLUE), self.paint("note", BOLD)).unwrap();
+            }
+        }
+
+        out
+    }
+}
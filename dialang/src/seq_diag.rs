@@ -1,28 +1,326 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
 use inline_xml::{xml_tag, Tag};
 
-use crate::{clean_ast::Class, style::Style};
+use crate::{clean_ast::{resolve_name, unqualified_name, Class, Method, Statement}, style::Style, xml::{escape_html, escape_xml}};
 
-pub(crate) fn make_seq_diag(classes: &HashMap<String, Class>) -> Vec<String> {
-    let mut id = 2;
-    let mut y = 25;
+const ACTOR_X: u32 = 55;
+const ACTOR_WIDTH: u32 = 30;
+const INSTANCE_WIDTH: u32 = 120;
+const HEAD_Y: u32 = 25;
+const HEAD_HEIGHT: u32 = 60;
+/// Distance between two lifelines
+const LIFELINE_SPACING: u32 = 180;
+/// Distance between two messages
+const MESSAGE_SPACING: u32 = 40;
+/// How far a call of a participant to itself goes right of its lifeline and down
+const SELF_CALL_SIZE: u32 = 30;
+/// Space for the operator and the guard of a combined fragment, or for the guard of its else section
+const FRAGMENT_HEADER: u32 = 40;
+/// Space between the messages and the border of a combined fragment, nested fragments get less of it
+const FRAGMENT_MARGIN: u32 = 60;
+const FRAGMENT_NESTING: u32 = 10;
+const FRAGMENT_MIN_WIDTH: u32 = 160;
 
-    seq_diag_from_classes(&mut id, &mut y, classes)
+/// UML combined fragment operators
+#[derive(Debug, Clone, Copy)]
+enum Fragment {
+    Alt,
+    Opt,
+    Loop
 }
 
-fn seq_diag_from_classes(id: &mut u32, y: &mut u32, classes: &HashMap<String, Class>) -> Vec<String> {
-    vec![make_actor(&id.to_string(), "comm-diag-1").to_string()]
+impl Display for Fragment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fragment::Alt => write!(f, "alt"),
+            Fragment::Opt => write!(f, "opt"),
+            Fragment::Loop => write!(f, "loop"),
+        }
+    }
+}
+
+/// Position and size of a combined fragment
+struct Bounds {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32
+}
+
+/// Someone taking part in the interaction, the first one is the actor
+struct Participant<'a> {
+    name: String,
+    class: Option<&'a Class>
+}
+
+/// A method being followed
+struct Frame<'a> {
+    class: &'a str,
+    method: &'a Method,
+    /// The participant the method returns to
+    caller: usize,
+    returned: bool
+}
+
+/// The diagram while the calls of the entry point are followed
+struct SeqDiag<'a> {
+    diag_id: &'a str,
+    classes: &'a HashMap<String, Class>,
+    participants: Vec<Participant<'a>>,
+    /// Methods being followed, a recursive call is not followed again
+    stack: Vec<Frame<'a>>,
+    /// First and last participants sending or receiving a message in the innermost combined fragment
+    extent: (usize, usize),
+    /// Number of combined fragments around the messages being drawn
+    depth: u32,
+    cells: Vec<String>,
+    id: u32,
+    y: u32
+}
+
+/// Makes the cells of the page `diag_id`, cell ids are prefixed with it so that they stay unique across pages
+pub(crate) fn make_seq_diag(diag_id: &str, entry_point: &[Statement], classes: &HashMap<String, Class>) -> Vec<String> {
+    let mut diag = SeqDiag {
+        diag_id,
+        classes,
+        participants: vec![Participant { name: "Actor".to_string(), class: None }],
+        stack: Vec::new(),
+        extent: (0, 0),
+        depth: 0,
+        cells: Vec::new(),
+        id: 2,
+        y: HEAD_Y + HEAD_HEIGHT + MESSAGE_SPACING
+    };
+
+    diag.follow_calls(0, entry_point);
+    diag.make_participants();
+
+    diag.cells
+}
+
+impl<'a> SeqDiag<'a> {
+    fn next_id(&mut self) -> String {
+        let id = format!("{}-{}", self.diag_id, self.id);
+        self.id += 1;
+
+        id
+    }
+
+    fn parent(&self) -> String {
+        format!("{}-1", self.diag_id)
+    }
+
+    /// Draws the heads and the lifelines, once every message is drawn so that the lifelines are long enough
+    fn make_participants(&mut self) {
+        let parent = self.parent();
+        let participants = std::mem::take(&mut self.participants);
+        for (i, participant) in participants.iter().enumerate() {
+            let id = self.next_id();
+            let head = match i {
+                0 => make_actor(&id, &parent),
+                _ => make_instance(&id, &parent, &participant.name, lifeline_x(i) - INSTANCE_WIDTH / 2),
+            };
+            self.cells.push(head.to_string());
+
+            let id = self.next_id();
+            self.cells.push(make_lifetime_line(&id, &parent, lifeline_x(i), HEAD_Y + HEAD_HEIGHT, self.y).to_string());
+        }
+        self.participants = participants;
+    }
+
+    /// Follows the calls made by the participant `caller`, and the calls made by the methods it calls
+    fn follow_calls(&mut self, caller: usize, statements: &'a [Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FuncCall { .. } => self.follow_call(caller, statement, None),
+                // The calls in the assigned value come first, the last one gives the value
+                Statement::Assignment { name, expr } => match expr.split_last() {
+                    Some((call @ Statement::FuncCall { .. }, before)) => {
+                        self.follow_calls(caller, before);
+                        self.follow_call(caller, call, Some(name));
+                    },
+                    _ => self.follow_calls(caller, expr),
+                },
+                Statement::If { then_branch, else_branch, .. } => self.follow_fragment(caller, statement, then_branch, else_branch),
+                Statement::While { body, .. } | Statement::For { body, .. } => self.follow_fragment(caller, statement, body, &[]),
+                Statement::Return { value } => if let Some(frame) = self.stack.last_mut() {
+                    frame.returned = true;
+                    let target = frame.caller;
+                    let label = get_return_label(value.as_deref(), frame.method.ret_type.as_deref());
+                    self.make_return(caller, target, &label);
+                },
+            }
+        }
+    }
+
+    /// Draws the message of `call` and follows the body of the called method
+    fn follow_call(&mut self, caller: usize, call: &'a Statement, assigned_to: Option<&str>) {
+        let Statement::FuncCall { receiver, name, .. } = call else {
+            return
+        };
+
+        let callee = self.callee(caller, receiver.as_deref());
+        self.make_call(caller, callee, &get_call_label(call, assigned_to).unwrap_or_default());
+        let method = self.participants[callee].class
+            .and_then(|class| class.methods.iter().find(|method| method.name == *name).map(|method| (class, method)));
+        if let Some((class, method)) = method {
+            if !self.stack.iter().any(|frame| frame.class == class.name && frame.method.name == method.name) {
+                self.stack.push(Frame { class: &class.name, method, caller, returned: false });
+                self.follow_calls(callee, &method.body);
+                // A method returning a value without `return` returns at the end of its body
+                if let Some(frame) = self.stack.pop() {
+                    if !frame.returned && method.ret_type.is_some() {
+                        self.make_return(callee, caller, &get_return_label(None, method.ret_type.as_deref()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the messages of `body` inside the fragment of `statement`, and those of `else_branch` in its else section.
+    /// The fragment is left out when nothing in it sends a message
+    fn follow_fragment(&mut self, caller: usize, statement: &Statement, body: &'a [Statement], else_branch: &'a [Statement]) {
+        let Some((fragment, guard)) = get_fragment(statement) else {
+            return
+        };
+
+        let index = self.cells.len();
+        let start_y = self.y;
+        let outer_extent = std::mem::replace(&mut self.extent, (caller, caller));
+        self.depth += 1;
+        self.y += FRAGMENT_HEADER;
+        self.follow_calls(caller, body);
+        let else_y = match else_branch.is_empty() {
+            true => None,
+            false => {
+                let else_y = self.y - MESSAGE_SPACING / 2;
+                self.y += FRAGMENT_HEADER;
+                self.follow_calls(caller, else_branch);
+                Some(else_y)
+            },
+        };
+        self.depth -= 1;
+        let (first, last) = std::mem::replace(&mut self.extent, outer_extent);
+        self.touch(first);
+        self.touch(last);
+
+        if self.cells.len() == index {
+            self.y = start_y;
+            return
+        }
+
+        // Messages are labelled above their arrow, the fragment starts above the label of the one before it
+        let margin = FRAGMENT_MARGIN - FRAGMENT_NESTING * self.depth.min(4);
+        let x = lifeline_x(first) - margin;
+        let bounds = Bounds {
+            x,
+            y: start_y - MESSAGE_SPACING / 2,
+            width: (lifeline_x(last) + margin - x).max(FRAGMENT_MIN_WIDTH),
+            height: self.y - start_y
+        };
+        let parent = self.parent();
+        let id = self.next_id();
+        let guard_id = self.next_id();
+        let mut cells = vec![];
+        let (frame, guard) = make_combined_fragment(&id, &guard_id, &parent, fragment, &guard, &bounds);
+        cells.push(frame.to_string());
+        cells.push(guard.to_string());
+        if let Some(else_y) = else_y {
+            let line_id = self.next_id();
+            let guard_id = self.next_id();
+            let (line, guard) = make_else_section(&line_id, &guard_id, &parent, &bounds, else_y);
+            cells.push(line.to_string());
+            cells.push(guard.to_string());
+        }
+        // Drawn under the messages
+        self.cells.splice(index..index, cells);
+
+        self.y += MESSAGE_SPACING / 2;
+    }
+
+    /// Widens the innermost combined fragment to the lifeline of the `i`th participant
+    fn touch(&mut self, i: usize) {
+        self.extent = (self.extent.0.min(i), self.extent.1.max(i));
+    }
+
+    fn make_call(&mut self, caller: usize, callee: usize, label: &str) {
+        self.touch(caller);
+        self.touch(callee);
+        let parent = self.parent();
+        let id = self.next_id();
+        let text_id = self.next_id();
+        let (line, text) = make_call_line(&id, &text_id, &parent, label, lifeline_x(caller), lifeline_x(callee), self.y);
+        self.cells.push(line.to_string());
+        self.cells.push(text.to_string());
+
+        self.y += MESSAGE_SPACING;
+        if caller == callee {
+            self.y += SELF_CALL_SIZE;
+        }
+    }
+
+    /// Returns to the same lifeline are left out, the call is already drawn going back to it
+    fn make_return(&mut self, source: usize, target: usize, label: &str) {
+        if source == target {
+            return
+        }
+
+        self.touch(source);
+        self.touch(target);
+        let parent = self.parent();
+        let id = self.next_id();
+        let text_id = self.next_id();
+        let (line, text) = make_return_line(&id, &text_id, &parent, label, lifeline_x(source), lifeline_x(target), self.y);
+        self.cells.push(line.to_string());
+        self.cells.push(text.to_string());
+
+        self.y += MESSAGE_SPACING;
+    }
+
+    /// The participant receiving a call of `caller`, an attribute receiving it stands for an instance of its type
+    fn callee(&mut self, caller: usize, receiver: Option<&str>) -> usize {
+        let receiver = match receiver.map(|receiver| receiver.strip_prefix("self.").unwrap_or(receiver)) {
+            None | Some("self") => return caller,
+            Some(receiver) => receiver,
+        };
+
+        let caller_class = self.participants[caller].class;
+        let type_name = caller_class
+            .and_then(|class| class.attributes.iter().find(|attribute| attribute.name == receiver))
+            .and_then(|attribute| attribute.r#type.as_deref())
+            .unwrap_or(receiver);
+        let package = caller_class.and_then(|class| class.package.as_deref());
+        let class = resolve_name(package, type_name, |name| self.classes.contains_key(name))
+            .and_then(|name| self.classes.get(&name));
+        let name = match class {
+            Some(class) => unqualified_name(&class.name).to_string(),
+            None => receiver.to_string(),
+        };
+
+        match self.participants.iter().position(|participant| participant.name == name) {
+            Some(i) => i,
+            None => {
+                self.participants.push(Participant { name, class });
+                self.participants.len() - 1
+            },
+        }
+    }
+}
+
+/// Horizontal position of the lifeline of the `i`th participant
+fn lifeline_x(i: usize) -> u32 {
+    ACTOR_X + ACTOR_WIDTH / 2 + i as u32 * LIFELINE_SPACING
 }
 
 fn make_actor(id: &str, parent: &str) -> Tag {
     let value = "Actor";
     let style = Style::default_actor().to_string();
     let vertex = "1";
-    let x = "55";
-    let y = "220";
-    let width = "30";
-    let height = "60";
+    let x = ACTOR_X.to_string();
+    let y = HEAD_Y.to_string();
+    let width = ACTOR_WIDTH.to_string();
+    let height = HEAD_HEIGHT.to_string();
     let r#as = "geometry";
 
     xml_tag!(
@@ -32,20 +330,14 @@ fn make_actor(id: &str, parent: &str) -> Tag {
     )
 }
 
-fn make_instance(id: &str, parent: &str) -> Tag {
-    /*
-    <mxCell id="AYnkgXlg9PiY_2FhFIBn-1" value="Class1" style="rounded=0;whiteSpace=wrap;html=1;" parent="seq-diag-1" vertex="1">
-        <mxGeometry x="190" y="220" width="120" height="60" as="geometry"/>
-    </mxCell>
-    */
-
-    let value = "ClassName";
+fn make_instance(id: &str, parent: &str, name: &str, x: u32) -> Tag {
+    let value = escape_xml(&escape_html(name));
     let style = Style::default_seq_class().to_string();
     let vertex = "1";
-    let x = "190";
-    let y = "220";
-    let width = "120";
-    let height = "60";
+    let x = x.to_string();
+    let y = HEAD_Y.to_string();
+    let width = INSTANCE_WIDTH.to_string();
+    let height = HEAD_HEIGHT.to_string();
     let r#as = "geometry";
 
     xml_tag!(
@@ -55,32 +347,31 @@ fn make_instance(id: &str, parent: &str) -> Tag {
     )
 }
 
-fn make_lifetime_line(id: &str, parent: &str, target: &str) -> Tag {
+fn make_lifetime_line(id: &str, parent: &str, x: u32, y_start: u32, y_end: u32) -> Tag {
     let value = "";
     let style = Style::default_lifetime_line().to_string();
     let edge = "1";
-    let width = "50";
-    let height = "50";
     let relative = "1";
     let as1 = "geometry";
-    let x1 = "250";
-    let y1 = "640";
+    let x = x.to_string();
+    let y1 = y_start.to_string();
     let as2 = "sourcePoint";
-    let x2 = "450";
-    let y2 = "380";
+    let y2 = y_end.to_string();
     let as3 = "targetPoint";
 
     xml_tag!(
-        <mxCell id={id} value={value} style={style} parent={parent} target={target} edge={edge}>
-            <mxGeometry width={width} height={height} relative={relative} as={as1}>
-                <mxPoint x={x1} y={y1} as={as2}/>
-                <mxPoint x={x2} y={y2} as={as3}/>
+        <mxCell id={id} value={value} style={style} parent={parent} edge={edge}>
+            <mxGeometry relative={relative} as={as1}>
+                <mxPoint x={x} y={y1} as={as2}/>
+                <mxPoint x={x} y={y2} as={as3}/>
             </mxGeometry>
         </mxCell>
     )
 }
 
-fn make_call_line(id: &str, text_id: &str, parent: &str, text: &str, target: &str) -> (Tag, Tag) {
+/// A call to the same lifeline goes right and comes back lower
+fn make_call_line(id: &str, text_id: &str, parent: &str, text: &str, source_x: u32, target_x: u32, y: u32) -> (Tag, Tag) {
+    let text = escape_xml(&escape_html(text));
     let value = "";
     let style = Style::default_call_arrow().to_string();
     let text_style = Style::default_call_text().to_string();
@@ -92,25 +383,43 @@ fn make_call_line(id: &str, text_id: &str, parent: &str, text: &str, target: &st
     let arrow_as1 = "geometry";
     let text_as1 = "geometry";
     let text_as2 = "offset";
-    let text_x = "0.1029";
-    let width = "50";
-    let height = "50";
-    let x1 = "70";
-    let x2 = "450";
-    let y1 = "320";
-    let y2 = "380";
+    let text_x = "0";
+    let x1 = source_x.to_string();
+    let x2 = target_x.to_string();
+    let y1 = y.to_string();
     let arrow_as2 = "sourcePoint";
     let arrow_as3 = "targetPoint";
 
-    (
+    let line = if source_x == target_x {
+        let y2 = (y + SELF_CALL_SIZE).to_string();
+        let x_right = (source_x + SELF_CALL_SIZE).to_string();
+        let arrow_as4 = "points";
+
         xml_tag!(
-            <mxCell id={id} value={value} style={style} parent={parent} target={target} edge={edge}>
-                <mxGeometry width={width} height={height} relative={relative} as={arrow_as1}>
+            <mxCell id={id} value={value} style={style} parent={parent} edge={edge}>
+                <mxGeometry relative={relative} as={arrow_as1}>
                     <mxPoint x={x1} y={y1} as={arrow_as2}/>
                     <mxPoint x={x2} y={y2} as={arrow_as3}/>
+                    <Array as={arrow_as4}>
+                        <mxPoint x={x_right} y={y1}/>
+                        <mxPoint x={x_right} y={y2}/>
+                    </Array>
                 </mxGeometry>
             </mxCell>
-        ),
+        )
+    } else {
+        xml_tag!(
+            <mxCell id={id} value={value} style={style} parent={parent} edge={edge}>
+                <mxGeometry relative={relative} as={arrow_as1}>
+                    <mxPoint x={x1} y={y1} as={arrow_as2}/>
+                    <mxPoint x={x2} y={y1} as={arrow_as3}/>
+                </mxGeometry>
+            </mxCell>
+        )
+    };
+
+    (
+        line,
         xml_tag!(
             <mxCell id={text_id} value={text} style={text_style} parent={id} vertex={vertex} connectable={connectable}>
                 <mxGeometry x={text_x} relative={text_relative} as={text_as1}>
@@ -121,11 +430,10 @@ fn make_call_line(id: &str, text_id: &str, parent: &str, text: &str, target: &st
     )
 }
 
-fn make_return_line(id: &str, text_id: &str, parent: &str, text: &str, target: &str, source: &str) -> (Tag, Tag) {
+fn make_return_line(id: &str, text_id: &str, parent: &str, text: &str, source_x: u32, target_x: u32, y: u32) -> (Tag, Tag) {
+    let text = escape_xml(&escape_html(text));
     let style = Style::default_return_arrow().to_string();
     let text_style = Style::default_return_text().to_string();
-    let width = "50";
-    let height = "50";
     let relative = "1";
     let text_relative = "1";
     let as1 = "geometry";
@@ -133,11 +441,10 @@ fn make_return_line(id: &str, text_id: &str, parent: &str, text: &str, target: &
     let as3 = "targetPoint";
     let text_as1 = "geometry";
     let text_as2 = "offset";
-    let x1 = "300";
-    let x2 = "70";
-    let y1 = "450";
-    let y2 = "510";
-    let text_x = "-0.1157";
+    let x1 = source_x.to_string();
+    let x2 = target_x.to_string();
+    let y = y.to_string();
+    let text_x = "0";
     let edge = "1";
     let vertex = "1";
     let connectable = "0";
@@ -145,10 +452,10 @@ fn make_return_line(id: &str, text_id: &str, parent: &str, text: &str, target: &
 
     (
         xml_tag!(
-            <mxCell id={id} value={value} style={style} parent={parent} source={source} edge={edge}>
-                <mxGeometry width={width} height={height} relative={relative} as={as1}>
-                    <mxPoint x={x1} y={y1} as={as2}/>
-                    <mxPoint x={x2} y={y2} as={as3}/>
+            <mxCell id={id} value={value} style={style} parent={parent} edge={edge}>
+                <mxGeometry relative={relative} as={as1}>
+                    <mxPoint x={x1} y={y} as={as2}/>
+                    <mxPoint x={x2} y={y} as={as3}/>
                 </mxGeometry>
             </mxCell>
         ),
@@ -161,3 +468,103 @@ fn make_return_line(id: &str, text_id: &str, parent: &str, text: &str, target: &
         )
     )
 }
+
+/// The fragment the body of a control flow statement is drawn in, with its guard
+fn get_fragment(statement: &Statement) -> Option<(Fragment, String)> {
+    match statement {
+        Statement::If { condition, else_branch, .. } if else_branch.is_empty() => Some((Fragment::Opt, condition.clone())),
+        Statement::If { condition, .. } => Some((Fragment::Alt, condition.clone())),
+        Statement::While { condition, .. } => Some((Fragment::Loop, condition.clone())),
+        Statement::For { variable, iterable, .. } => Some((Fragment::Loop, format!("for each {variable} in {iterable}"))),
+        Statement::Assignment { .. } | Statement::FuncCall { .. } | Statement::Return { .. } => None,
+    }
+}
+
+/// Label of a call message, `method(args)` with the arguments as they were written,
+/// or `variable = method(args)` when the result is assigned
+fn get_call_label(statement: &Statement, assigned_to: Option<&str>) -> Option<String> {
+    let Statement::FuncCall { name, args, .. } = statement else {
+        return None
+    };
+
+    let call = format!("{name}({})", args.join(", "));
+    match assigned_to {
+        Some(variable) => Some(format!("{variable} = {call}")),
+        None => Some(call),
+    }
+}
+
+/// `value: Type` when both are known
+fn get_return_label(value: Option<&str>, ret_type: Option<&str>) -> String {
+    match (value, ret_type) {
+        (Some(value), Some(ret_type)) => format!("{value}: {ret_type}"),
+        (Some(value), None) => value.to_string(),
+        (None, Some(ret_type)) => ret_type.to_string(),
+        (None, None) => "".to_string(),
+    }
+}
+
+/// The guard is placed under the operator, inside the frame
+fn make_combined_fragment(id: &str, guard_id: &str, parent: &str, fragment: Fragment, guard: &str, bounds: &Bounds) -> (Tag, Tag) {
+    let value = fragment.to_string();
+    let style = Style::default_combined_fragment().to_string();
+    let guard = escape_xml(&escape_html(&format!("[{guard}]")));
+    let guard_style = Style::default_guard().to_string();
+    let vertex = "1";
+    let Bounds { x, y, width, height } = bounds;
+    let guard_x = "10";
+    let guard_y = "20";
+    let guard_width = width - 20;
+    let guard_height = "20";
+    let as1 = "geometry";
+    let as2 = "geometry";
+
+    (
+        xml_tag!(
+            <mxCell id={id} value={value} style={style} parent={parent} vertex={vertex}>
+                <mxGeometry x={x} y={y} width={width} height={height} as={as1}/>
+            </mxCell>
+        ),
+        xml_tag!(
+            <mxCell id={guard_id} value={guard} style={guard_style} parent={id} vertex={vertex}>
+                <mxGeometry x={guard_x} y={guard_y} width={guard_width} height={guard_height} as={as2}/>
+            </mxCell>
+        )
+    )
+}
+
+/// The dashed line splitting an `alt` fragment at `y` and the `[else]` guard under it
+fn make_else_section(id: &str, guard_id: &str, parent: &str, bounds: &Bounds, y: u32) -> (Tag, Tag) {
+    let value = "";
+    let style = Style::default_lifetime_line().to_string();
+    let edge = "1";
+    let relative = "1";
+    let x1 = bounds.x.to_string();
+    let x2 = (bounds.x + bounds.width).to_string();
+    let as1 = "geometry";
+    let as2 = "sourcePoint";
+    let as3 = "targetPoint";
+    let guard = escape_xml("[else]");
+    let guard_style = Style::default_guard().to_string();
+    let vertex = "1";
+    let guard_x = bounds.x + 10;
+    let guard_width = bounds.width - 20;
+    let guard_height = "20";
+    let as4 = "geometry";
+
+    (
+        xml_tag!(
+            <mxCell id={id} value={value} style={style} parent={parent} edge={edge}>
+                <mxGeometry relative={relative} as={as1}>
+                    <mxPoint x={x1} y={y} as={as2}/>
+                    <mxPoint x={x2} y={y} as={as3}/>
+                </mxGeometry>
+            </mxCell>
+        ),
+        xml_tag!(
+            <mxCell id={guard_id} value={guard} style={guard_style} parent={parent} vertex={vertex}>
+                <mxGeometry x={guard_x} y={y} width={guard_width} height={guard_height} as={as4}/>
+            </mxCell>
+        )
+    )
+}
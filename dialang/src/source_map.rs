@@ -0,0 +1,45 @@
+/// Byte offsets of the start of each line of a file, turns the byte ranges of spans into lines and columns
+pub(crate) struct SourceMap {
+    line_starts: Vec<usize>
+}
+
+impl SourceMap {
+    pub(crate) fn new(text: &str) -> SourceMap {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        SourceMap {
+            line_starts
+        }
+    }
+
+    /// 0 based line containing `offset`
+    pub(crate) fn line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    /// 0 based line and column of `offset`, the column is counted in characters
+    pub(crate) fn line_col(&self, text: &str, offset: usize) -> (usize, usize) {
+        let offset = offset.min(text.len());
+        let line = self.line(offset);
+        let line_start = self.line_starts[line];
+        let column = match text.get(line_start..offset) {
+            Some(before) => before.chars().count(),
+            None => offset - line_start,
+        };
+
+        (line, column)
+    }
+
+    /// Text of the 0 based `line`, without its line ending
+    pub(crate) fn line_text<'a>(&self, text: &'a str, line: usize) -> &'a str {
+        let start = self.line_starts[line];
+        let end = self.line_starts.get(line + 1).copied().unwrap_or(text.len());
+
+        text[start..end].trim_end_matches(['\n', '\r'])
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use diaparser::{Diagnostic, Label, Severity, Span, TopLevelStatement};
+
+use crate::{report::Renderer, source_map::SourceMap};
+
+/// A file read while following imports, its `FileId` is its index in `Sources::files`
+pub(crate) struct SourceFile {
+    pub(crate) path: PathBuf,
+    pub(crate) content: String,
+    pub(crate) source_map: SourceMap
+}
+
+#[derive(Default)]
+pub(crate) struct Sources {
+    pub(crate) files: Vec<SourceFile>,
+    pub(crate) diagnostics: Vec<Diagnostic>
+}
+
+impl Sources {
+    /// Parses the file at `path` and the files it imports, imports are resolved relative to the importing file.
+    /// The statements of an imported file take the place of its import, files already imported are skipped.
+    /// Returns `None` if the file at `path` can't be read
+    pub(crate) fn parse_with_imports(&mut self, path: &Path) -> Option<Vec<(TopLevelStatement, Span)>> {
+        let mut statements = Vec::new();
+        self.parse_file(path, None, &mut Vec::new(), &mut statements);
+
+        match self.files.is_empty() {
+            true => None,
+            false => Some(statements),
+        }
+    }
+
+    /// Prints the diagnostics of all the files read, returns whether any of them is an error
+    pub(crate) fn report_diagnostics(&self, color: bool) -> bool {
+        let renderer = Renderer::new(&self.files, color);
+        for diagnostic in &self.diagnostics {
+            eprintln!("{}", renderer.render(diagnostic));
+        }
+
+        self.diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// Problems with the file given on the command line have no span to point to, so they are printed directly
+    fn report_import(&mut self, import: Option<Span>, severity: Severity, message: String, labels: Vec<Label>, note: String) {
+        match import {
+            Some(span) => self.diagnostics.push(Diagnostic {
+                span,
+                severity,
+                code: None,
+                message,
+                labels,
+                notes: vec![note]
+            }),
+            None => eprintln!("{severity}: {message}, {note}"),
+        }
+    }
+
+    /// `import` is the span of the path in the import statement, `importing` contains the files currently being parsed with the span of their import,
+    /// the last one being the one importing `path`
+    fn parse_file(&mut self, path: &Path, import: Option<Span>, importing: &mut Vec<(PathBuf, Option<Span>)>, statements: &mut Vec<(TopLevelStatement, Span)>) {
+        let path = match path.canonicalize() {
+            Ok(path) => path,
+            Err(e) => {
+                self.report_import(import, Severity::Error, format!("Cannot read {}", path.display()), Vec::new(), e.to_string());
+                return
+            },
+        };
+
+        if let Some(start) = importing.iter().position(|(file, _)| *file == path) {
+            let cycle = importing[start..].iter()
+                .map(|(file, _)| file)
+                .chain([&path])
+                .map(|file| file.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            // Where the cycle starts, unless the file imports itself
+            let labels = importing.get(start + 1)
+                .and_then(|(_, span)| span.clone())
+                .map(|span| Label { span, message: "the cycle starts with this import".to_string() })
+                .into_iter()
+                .collect();
+            self.report_import(import, Severity::Warning, "Import cycle, the import is ignored".to_string(), labels, format!("the cycle is {cycle}"));
+            return
+        }
+
+        if self.files.iter().any(|file| file.path == path) {
+            return
+        }
+
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                self.report_import(import, Severity::Error, format!("Cannot read {}", path.display()), Vec::new(), e.to_string());
+                return
+            },
+        };
+
+        let file_id = self.files.len();
+        let (tokens, diagnostics) = diaparser::tokenize(&content, file_id);
+        self.files.push(SourceFile {
+            path: path.clone(),
+            source_map: SourceMap::new(&content),
+            content
+        });
+        self.diagnostics.extend(diagnostics);
+
+        importing.push((path, import));
+        for (stmnt, span) in tokens {
+            match stmnt {
+                TopLevelStatement::Import(import) => {
+                    let imported = importing[importing.len()-1].0.parent()
+                        .map(|dir| dir.join(&import.path.0))
+                        .unwrap_or_else(|| PathBuf::from(&import.path.0));
+                    self.parse_file(&imported, Some(import.path.1), importing, statements)
+                },
+                stmnt => statements.push((stmnt, span)),
+            }
+        }
+        importing.pop();
+    }
+}
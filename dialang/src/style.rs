@@ -32,10 +32,17 @@ pub struct Style {
     outline_connect: Option<bool>,
     rounded: Option<bool>,
     white_space: Option<WhiteSpace>,
-    entryx: Option<f64>,
-    entryy: Option<f64>,
-    entrydx: Option<f64>,
-    entrydy: Option<f64>,
+    end_arrow: Option<Arrow>,
+    end_size: Option<u32>,
+    end_fill: Option<bool>,
+    start_arrow: Option<Arrow>,
+    start_fill: Option<bool>,
+    dashed: Option<bool>,
+    resizable: Option<bool>,
+    tab_width: Option<u32>,
+    tab_height: Option<u32>,
+    tab_position: Option<Alignment>,
+    container: Option<bool>,
 }
 
 impl Style {
@@ -57,9 +64,9 @@ impl Style {
             if base_style.stroke_color_able() {
                 if let Some(stroke_color) = self.stroke_color {
                     todo!();
-                    string += &format!("strokeColor=none;")
+                    string += "strokeColor=none;"
                 } else {
-                    string += &format!("strokeColor=none;")
+                    string += "strokeColor=none;"
                 }
             }
 
@@ -72,9 +79,9 @@ impl Style {
             if base_style.fill_color_able() {
                 if let Some(fill_color) = self.fill_color {
                     todo!();
-                    string += &format!("fillColor=none;")
+                    string += "fillColor=none;"
                 } else {
-                    string += &format!("fillColor=none;")
+                    string += "fillColor=none;"
                 }
             }
         }
@@ -179,6 +186,64 @@ impl Style {
             string += &format!("marginBottom={margin_bottom};")
         }
 
+        if let Some(tab_width) = self.tab_width {
+            string += &format!("tabWidth={tab_width};")
+        }
+
+        if let Some(tab_height) = self.tab_height {
+            string += &format!("tabHeight={tab_height};")
+        }
+
+        if let Some(tab_position) = self.tab_position {
+            string += &format!("tabPosition={tab_position};")
+        }
+
+        if let Some(container) = self.container {
+            let container = bool_to_num(container);
+            string += &format!("container={container};")
+        }
+
+        if let Some(end_arrow) = self.end_arrow {
+            string += &format!("endArrow={end_arrow};")
+        }
+
+        if let Some(end_size) = self.end_size {
+            string += &format!("endSize={end_size};")
+        }
+
+        if let Some(end_fill) = self.end_fill {
+            let end_fill = bool_to_num(end_fill);
+            string += &format!("endFill={end_fill};")
+        }
+
+        if let Some(start_arrow) = self.start_arrow {
+            string += &format!("startArrow={start_arrow};")
+        }
+
+        if let Some(start_fill) = self.start_fill {
+            let start_fill = bool_to_num(start_fill);
+            string += &format!("startFill={start_fill};")
+        }
+
+        if let Some(dashed) = self.dashed {
+            let dashed = bool_to_num(dashed);
+            string += &format!("dashed={dashed};")
+        }
+
+        if let Some(resizable) = self.resizable {
+            let resizable = bool_to_num(resizable);
+            string += &format!("resizable={resizable};")
+        }
+
+        if let Some(rounded) = self.rounded {
+            let rounded = bool_to_num(rounded);
+            string += &format!("rounded={rounded};")
+        }
+
+        if let Some(white_space) = self.white_space {
+            string += &format!("whiteSpace={white_space};")
+        }
+
         if let Some(html) = self.html {
             let html = if html {
                 1
@@ -219,8 +284,9 @@ impl Style {
         style
     }
 
-    pub fn default_text() -> Style {
-        let style = StyleBuilder::new()
+    /// The rows of attributes, methods and variants, without a font style
+    fn text_builder() -> StyleBuilder {
+        StyleBuilder::new()
             .with_base_style(BaseStyle::Text)
             .with_alignment(Alignment::Left)
             .with_vertical_alignment(VerticalAlignment::Top)
@@ -230,6 +296,16 @@ impl Style {
             .with_rotatable(false)
             .with_points(vec![[0.0,0.5],[1.0,0.5]])
             .with_port_constraint(PortConstraint::EastWest)
+    }
+
+    pub fn text_with_font_style(font_style: u32) -> Style {
+        Self::text_builder()
+            .with_font_style(font_style)
+            .build()
+    }
+
+    pub fn default_text() -> Style {
+        let style = Self::text_builder()
             .build();
 
         debug_assert_eq!(style.clone().to_string(), "text;strokeColor=none;fillColor=none;align=left;verticalAlign=top;spacingLeft=4;spacingRight=4;overflow=hidden;rotatable=0;points=[[0,0.5],[1,0.5]];portConstraint=eastwest;");
@@ -237,29 +313,48 @@ impl Style {
         style
     }
 
-    pub fn default_swimlane() -> Style {
-        //"swimlane;fontStyle=1;align=center;verticalAlign=top;childLayout=stackLayout;horizontal=1;startSize=26;horizontalStack=0;resizeParent=1;resizeParentMax=0;resizeLast=0;collapsible=1;marginBottom=0;"
-        let style = StyleBuilder::new()
+    /// The box of classes and enums, `start_size` is the height of the title
+    fn swimlane_builder(start_size: u32) -> StyleBuilder {
+        StyleBuilder::new()
             .with_base_style(BaseStyle::SwimLane)
-            .with_font_style(1)
             .with_alignment(Alignment::Center)
             .with_vertical_alignment(VerticalAlignment::Top)
             .with_child_layout(ChildLayout::Stack)
             .with_horizontal(true)
-            .with_start_size(26)
+            .with_start_size(start_size)
             .with_horizontal_stack(false)
             .with_resize_parent(true)
             .with_resize_parent_max(0)
             .with_resize_last(false)
             .with_collapsible(true)
             .with_margin_bottom(0)
-            .build();
+    }
+
+    pub fn default_swimlane() -> Style {
+        let style = Self::swimlane_with_font_style(FONT_STYLE_BOLD);
 
         debug_assert_eq!(style.clone().to_string(), "swimlane;fontStyle=1;align=center;verticalAlign=top;childLayout=stackLayout;horizontal=1;startSize=26;horizontalStack=0;resizeParent=1;resizeParentMax=0;resizeLast=0;collapsible=1;marginBottom=0;");
 
         style
     }
 
+    pub fn swimlane_with_font_style(font_style: u32) -> Style {
+        Self::swimlane_builder(26)
+            .with_font_style(font_style)
+            .build()
+    }
+
+    pub fn default_stereotype_swimlane() -> Style {
+        let style = Self::swimlane_builder(40)
+            .with_font_style(0)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "swimlane;fontStyle=0;align=center;verticalAlign=top;childLayout=stackLayout;horizontal=1;startSize=40;horizontalStack=0;resizeParent=1;resizeParentMax=0;resizeLast=0;collapsible=1;marginBottom=0;html=1;");
+
+        style
+    }
+
     pub fn default_actor() -> Style {
         let style = StyleBuilder::new()
             .with_shape(Shape::UMLActor)
@@ -288,41 +383,44 @@ impl Style {
 
     pub(crate) fn default_lifetime_line() -> Style {
         let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::None)
+            .with_dashed(true)
             .with_html(true)
-            .with_entryx(0.5)
-            .with_entryy(1.0)
-            .with_entrydx(0.0)
-            .with_entrydy(0.0)
             .build();
 
-        debug_assert_eq!(style.clone().to_string(), "endArrow=none;html=1;entryX=0.5;entryY=1;entryDx=0;entryDy=0;");
+        debug_assert_eq!(style.clone().to_string(), "endArrow=none;dashed=1;html=1;");
 
         style
     }
 
     pub(crate) fn default_call_arrow() -> Style {
         let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Block)
+            .with_end_fill(true)
+            .with_html(true)
             .build();
 
-        debug_assert_eq!(style.clone().to_string(), "endArrow=classic;html=1;entryX=0.25;entryY=0;entryDx=0;entryDy=0;");
+        debug_assert_eq!(style.clone().to_string(), "endArrow=block;endFill=1;html=1;");
 
         style
     }
 
     pub(crate) fn default_call_text() -> Style {
-        let style = StyleBuilder::new()
-            .build();
+        let style = Self::edge_label_with_alignment(Alignment::Center, VerticalAlignment::Bottom);
 
-        debug_assert_eq!(style.clone().to_string(), "edgeLabel;html=1;align=center;verticalAlign=middle;resizable=0;points=[];");
+        debug_assert_eq!(style.clone().to_string(), "edgeLabel;align=center;verticalAlign=bottom;points=[];resizable=0;html=1;");
 
         style
     }
 
     pub(crate) fn default_return_arrow() -> Style {
         let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Open)
+            .with_dashed(true)
+            .with_html(true)
             .build();
 
-        debug_assert_eq!(style.clone().to_string(), "endArrow=classic;html=1;exitX=0;exitY=1;exitDx=0;exitDy=0;dashed=1;");
+        debug_assert_eq!(style.clone().to_string(), "endArrow=open;dashed=1;html=1;");
 
         style
     }
@@ -330,8 +428,194 @@ impl Style {
     pub(crate) fn default_return_text() -> Style {
         Self::default_call_text()
     }
+
+    pub(crate) fn default_combined_fragment() -> Style {
+        let style = StyleBuilder::new()
+            .with_shape(Shape::UMLFrame)
+            .with_white_space(WhiteSpace::Wrap)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "shape=umlFrame;whiteSpace=wrap;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_guard() -> Style {
+        let style = StyleBuilder::new()
+            .with_base_style(BaseStyle::Text)
+            .with_alignment(Alignment::Left)
+            .with_vertical_alignment(VerticalAlignment::Top)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "text;strokeColor=none;fillColor=none;align=left;verticalAlign=top;points=[];html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_realization() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Block)
+            .with_end_size(16)
+            .with_end_fill(false)
+            .with_dashed(true)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=block;endSize=16;endFill=0;dashed=1;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_generalization() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Block)
+            .with_end_size(16)
+            .with_end_fill(false)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=block;endSize=16;endFill=0;html=1;");
+
+        style
+    }
+
+    pub(crate) fn edge_label_with_alignment(alignment: Alignment, vertical_alignment: VerticalAlignment) -> Style {
+        StyleBuilder::new()
+            .with_base_style(BaseStyle::EdgeLabel)
+            .with_alignment(alignment)
+            .with_vertical_alignment(vertical_alignment)
+            .with_resizable(false)
+            .with_html(true)
+            .build()
+    }
+
+    pub(crate) fn default_edge_label() -> Style {
+        let style = Self::edge_label_with_alignment(Alignment::Center, VerticalAlignment::Middle);
+
+        debug_assert_eq!(style.clone().to_string(), "edgeLabel;align=center;verticalAlign=middle;points=[];resizable=0;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_association() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::None)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=none;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_directed_association() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Open)
+            .with_end_size(12)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=open;endSize=12;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_aggregation() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::None)
+            .with_start_arrow(Arrow::DiamondThin)
+            .with_start_size(14)
+            .with_start_fill(false)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "startSize=14;endArrow=none;startArrow=diamondThin;startFill=0;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_composition() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::None)
+            .with_start_arrow(Arrow::DiamondThin)
+            .with_start_size(14)
+            .with_start_fill(true)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "startSize=14;endArrow=none;startArrow=diamondThin;startFill=1;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_dependency() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::Open)
+            .with_end_size(12)
+            .with_dashed(true)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=open;endSize=12;dashed=1;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_note() -> Style {
+        let style = StyleBuilder::new()
+            .with_shape(Shape::Note)
+            .with_alignment(Alignment::Left)
+            .with_vertical_alignment(VerticalAlignment::Top)
+            .with_spacing_left(4)
+            .with_spacing_right(4)
+            .with_white_space(WhiteSpace::Wrap)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "shape=note;align=left;verticalAlign=top;spacingLeft=4;spacingRight=4;whiteSpace=wrap;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_note_link() -> Style {
+        let style = StyleBuilder::new()
+            .with_end_arrow(Arrow::None)
+            .with_dashed(true)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "endArrow=none;dashed=1;html=1;");
+
+        style
+    }
+
+    pub(crate) fn default_package() -> Style {
+        let style = StyleBuilder::new()
+            .with_font_style(FONT_STYLE_BOLD)
+            .with_shape(Shape::Folder)
+            .with_alignment(Alignment::Left)
+            .with_vertical_alignment(VerticalAlignment::Top)
+            .with_spacing_left(10)
+            .with_tab_width(80)
+            .with_tab_height(20)
+            .with_tab_position(Alignment::Left)
+            .with_container(true)
+            .with_html(true)
+            .build();
+
+        debug_assert_eq!(style.clone().to_string(), "fontStyle=1;shape=folder;align=left;verticalAlign=top;spacingLeft=10;tabWidth=80;tabHeight=20;tabPosition=left;container=1;html=1;");
+
+        style
+    }
 }
 
+/// `fontStyle` bit flags, combine them with `|`
+pub const FONT_STYLE_BOLD: u32 = 1;
+pub const FONT_STYLE_ITALIC: u32 = 2;
+pub const FONT_STYLE_UNDERLINE: u32 = 4;
+
 fn bool_to_num(b: bool) -> u32 {
     if b {
         1
@@ -345,15 +629,48 @@ pub enum WhiteSpace {
     Wrap
 }
 
+impl Display for WhiteSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WhiteSpace::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Shape {
-    UMLActor
+    UMLActor,
+    Folder,
+    Note,
+    UMLFrame
 }
 
 impl Display for Shape {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Shape::UMLActor => write!(f, "umlActor"),
+            Shape::Folder => write!(f, "folder"),
+            Shape::Note => write!(f, "note"),
+            Shape::UMLFrame => write!(f, "umlFrame"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arrow {
+    Block,
+    Open,
+    DiamondThin,
+    None
+}
+
+impl Display for Arrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arrow::Block => write!(f, "block"),
+            Arrow::Open => write!(f, "open"),
+            Arrow::DiamondThin => write!(f, "diamondThin"),
+            Arrow::None => write!(f, "none"),
         }
     }
 }
@@ -362,7 +679,8 @@ impl Display for Shape {
 pub enum BaseStyle {
     Line,
     Text,
-    SwimLane
+    SwimLane,
+    EdgeLabel
 }
 
 impl BaseStyle {
@@ -371,6 +689,7 @@ impl BaseStyle {
             BaseStyle::Line => false,
             BaseStyle::Text => true,
             BaseStyle::SwimLane => false,
+            BaseStyle::EdgeLabel => false,
         }
     }
 
@@ -379,6 +698,7 @@ impl BaseStyle {
             BaseStyle::Line => true,
             BaseStyle::Text => true,
             BaseStyle::SwimLane => false,
+            BaseStyle::EdgeLabel => false,
         }
     }
 
@@ -387,6 +707,7 @@ impl BaseStyle {
             BaseStyle::Line => true,
             BaseStyle::Text => false,
             BaseStyle::SwimLane => false,
+            BaseStyle::EdgeLabel => false,
         }
     }
 
@@ -395,6 +716,7 @@ impl BaseStyle {
             BaseStyle::Line => true,
             BaseStyle::Text => true,
             BaseStyle::SwimLane => false,
+            BaseStyle::EdgeLabel => true,
         }
     }
 }
@@ -405,6 +727,7 @@ impl Display for BaseStyle {
             BaseStyle::Line => write!(f, "line"),
             BaseStyle::Text => write!(f, "text"),
             BaseStyle::SwimLane => write!(f, "swimlane"),
+            BaseStyle::EdgeLabel => write!(f, "edgeLabel"),
         }
     }
 }
@@ -412,7 +735,8 @@ impl Display for BaseStyle {
 #[derive(Debug, Clone, Copy)]
 pub enum Alignment {
     Left,
-    Center
+    Center,
+    Right
 }
 
 impl Display for Alignment {
@@ -420,6 +744,7 @@ impl Display for Alignment {
         match self {
             Alignment::Left => write!(f, "left"),
             Alignment::Center => write!(f, "center"),
+            Alignment::Right => write!(f, "right"),
         }
     }
 }
@@ -427,7 +752,8 @@ impl Display for Alignment {
 #[derive(Debug, Clone, Copy)]
 pub enum VerticalAlignment {
     Top,
-    Middle
+    Middle,
+    Bottom
 }
 
 impl Display for VerticalAlignment {
@@ -435,6 +761,7 @@ impl Display for VerticalAlignment {
         match self {
             VerticalAlignment::Top => write!(f, "top"),
             VerticalAlignment::Middle => write!(f, "middle"),
+            VerticalAlignment::Bottom => write!(f, "bottom"),
         }
     }
 }
@@ -524,10 +851,17 @@ pub struct StyleBuilder {
     outline_connect: Option<bool>,
     rounded: Option<bool>,
     white_space: Option<WhiteSpace>,
-    entryx: Option<f64>,
-    entryy: Option<f64>,
-    entrydx: Option<f64>,
-    entrydy: Option<f64>,
+    end_arrow: Option<Arrow>,
+    end_size: Option<u32>,
+    end_fill: Option<bool>,
+    start_arrow: Option<Arrow>,
+    start_fill: Option<bool>,
+    dashed: Option<bool>,
+    resizable: Option<bool>,
+    tab_width: Option<u32>,
+    tab_height: Option<u32>,
+    tab_position: Option<Alignment>,
+    container: Option<bool>,
 }
 
 impl StyleBuilder {
@@ -563,10 +897,17 @@ impl StyleBuilder {
             outline_connect: None,
             rounded: None,
             white_space: None,
-            entryx: None,
-            entryy: None,
-            entrydx: None,
-            entrydy: None,
+            end_arrow: None,
+            end_size: None,
+            end_fill: None,
+            start_arrow: None,
+            start_fill: None,
+            dashed: None,
+            resizable: None,
+            tab_width: None,
+            tab_height: None,
+            tab_position: None,
+            container: None,
         }
     }
 
@@ -746,10 +1087,17 @@ impl StyleBuilder {
             outline_connect: self.outline_connect,
             rounded: self.rounded,
             white_space: self.white_space,
-            entryx: self.entryx,
-            entryy: self.entryy,
-            entrydx: self.entrydx,
-            entrydy: self.entrydy,
+            end_arrow: self.end_arrow,
+            end_size: self.end_size,
+            end_fill: self.end_fill,
+            start_arrow: self.start_arrow,
+            start_fill: self.start_fill,
+            dashed: self.dashed,
+            resizable: self.resizable,
+            tab_width: self.tab_width,
+            tab_height: self.tab_height,
+            tab_position: self.tab_position,
+            container: self.container,
         }
     }
 
@@ -789,26 +1137,72 @@ impl StyleBuilder {
         self
     }
 
-    fn with_entryx(mut self, entryx: f64) -> Self {
-        self.entryx = Some(entryx);
+
+
+
+
+    fn with_end_arrow(mut self, end_arrow: Arrow) -> Self {
+        self.end_arrow = Some(end_arrow);
+
+        self
+    }
+
+    fn with_end_size(mut self, end_size: u32) -> Self {
+        self.end_size = Some(end_size);
+
+        self
+    }
+
+    fn with_end_fill(mut self, end_fill: bool) -> Self {
+        self.end_fill = Some(end_fill);
+
+        self
+    }
+
+    fn with_start_arrow(mut self, start_arrow: Arrow) -> Self {
+        self.start_arrow = Some(start_arrow);
+
+        self
+    }
+
+    fn with_start_fill(mut self, start_fill: bool) -> Self {
+        self.start_fill = Some(start_fill);
+
+        self
+    }
+
+    fn with_dashed(mut self, dashed: bool) -> Self {
+        self.dashed = Some(dashed);
+
+        self
+    }
+
+    fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = Some(resizable);
+
+        self
+    }
+
+    fn with_tab_width(mut self, tab_width: u32) -> Self {
+        self.tab_width = Some(tab_width);
 
         self
     }
 
-    fn with_entryy(mut self, entryy: f64) -> Self {
-        self.entryy = Some(entryy);
+    fn with_tab_height(mut self, tab_height: u32) -> Self {
+        self.tab_height = Some(tab_height);
 
         self
     }
 
-    fn with_entrydx(mut self, entrydx: f64) -> Self {
-        self.entrydx = Some(entrydx);
+    fn with_tab_position(mut self, tab_position: Alignment) -> Self {
+        self.tab_position = Some(tab_position);
 
         self
     }
 
-    fn with_entrydy(mut self, entrydy: f64) -> Self {
-        self.entrydy = Some(entrydy);
+    fn with_container(mut self, container: bool) -> Self {
+        self.container = Some(container);
 
         self
     }
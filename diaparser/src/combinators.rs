@@ -1,8 +1,6 @@
-use std::ops::Range;
-
 use nom::{error::{ParseError, ErrorKind}, Parser, InputLength};
 use nom::Err;
-use crate::{token::Spanned, recovery_err::{StrResult, ToRange}};
+use crate::{token::{Span, Spanned}, recovery_err::{StrResult, ToRange}};
 
 pub fn spanned<I, O, E: ParseError<I>, F>(
     mut parser: F
@@ -13,7 +11,7 @@ where
 {
     move |i: I| {
         let (remaining, o) = parser.parse(i)?;
-        let span = i.consumed_span(remaining.span().start);
+        let span = i.consumed_span(remaining.span().range.start);
 
         Ok((remaining, (o, span)))
     }
@@ -26,11 +24,11 @@ pub fn map_with_span<I, O1, O2, E: ParseError<I>, F, G>(
 where
   F: Parser<I, O1, E>,
   I: ToRange + Copy,
-  G: FnMut(O1, Range<usize>) -> O2,
+  G: FnMut(O1, Span) -> O2,
 {
     move |i: I| {
         let (remaining, o) = parser.parse(i)?;
-        let span = i.consumed_span(remaining.span().start);
+        let span = i.consumed_span(remaining.span().range.start);
 
         Ok((remaining, mapper(o, span)))
     }
@@ -61,7 +59,10 @@ where
     move |i: I| {
         let full_span = i.span();
         let (remaining, _) = parser.parse(i)?;
-        let span = full_span.start..remaining.span().start;
+        let span = Span {
+            file: full_span.file,
+            range: full_span.range.start..remaining.span().range.start
+        };
 
         Ok((remaining, (value.clone(), span)))
     }
@@ -0,0 +1,82 @@
+use std::fmt;
+
+use crate::{token::Span, Severity};
+
+/// Problems found while reading a file, each kind has a stable code so it can be looked up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParserError {
+    /// A character that can't start any token
+    UnknownCharacter { span: Span, found: char },
+    /// `/*` without its matching `*/`, the span is the opening `/*`
+    UnterminatedBlockComment { span: Span },
+    /// `found` is `None` at the end of the file
+    Expected { span: Span, expected: &'static str, found: Option<String> },
+    /// A token that can't start a statement
+    UnexpectedToken { span: Span, found: Option<String> },
+    /// A block missing its `}`, `opening` is the `{` it should close
+    UnclosedBrace { span: Span, opening: Span, found: Option<String> },
+    /// A doc comment on a statement that doesn't keep it, `ignored_by` names the statement kind
+    IgnoredDocComment { span: Span, ignored_by: &'static str },
+}
+
+impl ParserError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::UnknownCharacter { .. } => "E0001",
+            ParserError::UnterminatedBlockComment { .. } => "E0002",
+            ParserError::Expected { .. } => "E0003",
+            ParserError::UnexpectedToken { .. } => "E0004",
+            ParserError::UnclosedBrace { .. } => "E0005",
+            ParserError::IgnoredDocComment { .. } => "W0001",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ParserError::IgnoredDocComment { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            ParserError::UnknownCharacter { span, .. } => span,
+            ParserError::UnterminatedBlockComment { span } => span,
+            ParserError::Expected { span, .. } => span,
+            ParserError::UnexpectedToken { span, .. } => span,
+            ParserError::UnclosedBrace { span, .. } => span,
+            ParserError::IgnoredDocComment { span, .. } => span,
+        }
+    }
+}
+
+fn write_found(f: &mut fmt::Formatter, found: &Option<String>) -> fmt::Result {
+    match found {
+        Some(found) => write!(f, "`{found}`"),
+        None => write!(f, "end of file"),
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParserError::UnknownCharacter { found, .. } => write!(f, "Unknown character `{found}`"),
+            ParserError::UnterminatedBlockComment { .. } => write!(f, "Unterminated block comment"),
+            ParserError::Expected { expected, found, .. } => {
+                write!(f, "Expected {expected}, found ")?;
+                write_found(f, found)
+            },
+            ParserError::UnexpectedToken { found, .. } => {
+                write!(f, "Unexpected ")?;
+                write_found(f, found)
+            },
+            ParserError::UnclosedBrace { found, .. } => {
+                write!(f, "Expected `}}`, found ")?;
+                write_found(f, found)
+            },
+            ParserError::IgnoredDocComment { ignored_by, .. } => write!(f, "Doc comments on {ignored_by} are ignored"),
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
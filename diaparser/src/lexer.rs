@@ -2,38 +2,46 @@ use std::cell::RefCell;
 
 use nom::{
     character::complete::{
-        alpha1,
-        alphanumeric1,
+        digit1,
         multispace1,
-        not_line_ending
+        not_line_ending,
+        satisfy
     },
     branch::alt as choice,
     bytes::complete::{
-        tag, take_until
+        tag, take_until, take_while
     },
     combinator::{
         recognize,
         eof,
         map,
-        map_opt
+        not
     },
-    sequence::{pair, preceded, delimited},
+    sequence::{pair, preceded, delimited, tuple},
     multi::{
-        many0, many_till
+        many_till
     },
     InputTake
 };
 use nom_supreme::error::{GenericErrorTree, ErrorTree};
+use unicode_ident::{is_xid_continue, is_xid_start};
 
-use crate::{recovery_err::{ParseState, RecoveredError, StrResult, StrSpan, ToRange}, token::{FromStrSpan, Keyword, TokSpan, Token}, Annotation};
+use crate::{recovery_err::{ParseState, StrResult, StrSpan, ToRange}, token::{FileId, FromStrSpan, Keyword, Span, TokSpan, Token}, ParserError};
 
 fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSpan<'a, 'b>>> {
     // A parser for operators
     let op = map(
-        tag("="),
+        choice((
+            tag("-->"),
+            tag("--"),
+            tag("*--"),
+            tag("o--"),
+            tag("..>"),
+            tag("="),
+        )),
         |s: StrSpan| {
             let state = s.extra;
-            TokSpan::from_strspan(Token::Op(s.fragment()), state, s.span())
+            TokSpan::from_strspan(Token::Op(s.fragment()), state, s.span(), s.location_line())
         }
     );
 
@@ -47,41 +55,47 @@ fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSp
             tag(","),
             tag("."),
             tag(":"),
-            tag("#")
+            tag("#"),
+            tag("+"),
+            tag("-"),
+            tag("~"),
+            tag("<"),
+            tag(">"),
+            tag("&"),
+            tag("["),
+            tag("]"),
+            tag(";")
         )),
         |s: StrSpan| {
             let state = s.extra;
             TokSpan::from_strspan(
                 Token::Separator(s.fragment().chars().next().unwrap()),
                 state,
-                s.span()
+                s.span(),
+                s.location_line()
             )
         }
     );
 
-    let annotation = map_opt(
-        preceded(
+    // A parser for annotations, which ones exist is checked after parsing. The token is the name, its span starts at the `@`
+    let annotation = map(
+        pair(
             tag("@"),
-            tag("SequenceEntrypoint"),
+            identifier
         ),
-        |s: StrSpan| {
-            let token = match *s.fragment() {
-                "SequenceEntrypoint" => Some(Token::A(Annotation::SequenceEntrypoint)),
-                _ => None
-            };
+        |(at, s): (StrSpan, StrSpan)| {
             let state = s.extra;
-            token.map(|token| TokSpan::from_strspan(token, state, s.span()))
+            let span = Span {
+                file: state.1,
+                range: at.location_offset()..s.span().range.end
+            };
+            TokSpan::from_strspan(Token::A(s.fragment()), state, span, s.location_line())
         }
     );
 
     // A parser for identifiers and keywords
     let ident = map(
-        recognize(
-            pair(
-                choice((alpha1, tag("_"))),
-                many0(choice((alphanumeric1, tag("_"))))
-            )
-        ),
+        identifier,
         |s: StrSpan| {
             let token = match *s.fragment() {
                 "fn" => Token::K(Keyword::Fn),
@@ -92,10 +106,45 @@ fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSp
                 "in" => Token::K(Keyword::In),
                 "class" => Token::K(Keyword::Class),
                 "struct" => Token::K(Keyword::Class),
+                "extends" => Token::K(Keyword::Extends),
+                "interface" => Token::K(Keyword::Interface),
+                "trait" => Token::K(Keyword::Interface),
+                "implements" => Token::K(Keyword::Implements),
+                "impl" => Token::K(Keyword::Impl),
+                "pub" => Token::K(Keyword::Pub),
+                "priv" => Token::K(Keyword::Priv),
+                "static" => Token::K(Keyword::Static),
+                "abstract" => Token::K(Keyword::Abstract),
+                "mut" => Token::K(Keyword::Mut),
+                "enum" => Token::K(Keyword::Enum),
+                "package" | "mod" => Token::K(Keyword::Package),
+                "note" => Token::K(Keyword::Note),
+                "return" => Token::K(Keyword::Return),
+                "import" => Token::K(Keyword::Import),
+                "true" => Token::Bool(true),
+                "false" => Token::Bool(false),
                 s => Token::Ident(s)
             };
             let state = s.extra;
-            TokSpan::from_strspan(token, state, s.span())
+            TokSpan::from_strspan(token, state, s.span(), s.location_line())
+        }
+    );
+
+    // A parser for float literals
+    let float = map(
+        recognize(tuple((digit1, tag("."), digit1))),
+        |s: StrSpan| {
+            let state = s.extra;
+            TokSpan::from_strspan(Token::Float(s.fragment()), state, s.span(), s.location_line())
+        }
+    );
+
+    // A parser for integer literals
+    let int = map(
+        digit1,
+        |s: StrSpan| {
+            let state = s.extra;
+            TokSpan::from_strspan(Token::Int(s.fragment()), state, s.span(), s.location_line())
         }
     );
 
@@ -106,33 +155,60 @@ fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSp
             tag("`")
         ),
         |s: StrSpan| {
-            TokSpan::from_strspan(Token::Ident(s.fragment()), s.extra, s.span())
+            TokSpan::from_strspan(Token::Ident(s.fragment()), s.extra, s.span(), s.location_line())
+        }
+    );
+
+    // A parser for string literals
+    let string = map(
+        delimited(
+            tag("\""),
+            recognize(take_until("\"")),
+            tag("\"")
+        ),
+        |s: StrSpan| {
+            TokSpan::from_strspan(Token::Str(s.fragment()), s.extra, s.span(), s.location_line())
         }
     );
 
     // A single token can be one of the above
     let token = choice((
         raw,
+        string,
         op,
         ctrl,
         annotation,
+        float,
+        int,
         ident,
     ));
 
+    // A parser for doc comments, kept as tokens unlike other comments. `////` is a regular comment
+    let doc = map(
+        preceded(
+            pair(tag("///"), not(tag("/"))),
+            not_line_ending
+        ),
+        |s: StrSpan| {
+            TokSpan::from_strspan(Token::Doc(s.fragment()), s.extra, s.span(), s.location_line())
+        }
+    );
+
     let comment = preceded(tag("//"), not_line_ending);
 
     let padding = map(
         choice((
+            block_comment,
             comment,
             multispace1
         )),
         |s: StrSpan| {
             let state = s.extra;
-            TokSpan::from_strspan(Token::Comment(s.fragment()), state, s.span())
+            TokSpan::from_strspan(Token::Comment(s.fragment()), state, s.span(), s.location_line())
         }
     );
 
-    let mut pos_inputs = choice((padding, token));
+    let mut pos_inputs = choice((doc, padding, token));
 
     map(
         many_till(
@@ -140,12 +216,13 @@ fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSp
                 Ok(r) => Ok(r),
                 Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
                     let input = recover_err(&e);
-                    let len = input.fragment().chars().next().unwrap().len_utf8();
-                    let (rest, input) = input.take_split(len);
+                    let found = input.fragment().chars().next().unwrap();
+                    let (rest, input) = input.take_split(found.len_utf8());
                     let span = input.span();
-                    input.extra.report_error(RecoveredError(span.clone(), "Unkown (non-ASCII) character".to_string()));
+                    let line = input.location_line();
+                    input.extra.report_error(ParserError::UnknownCharacter { span: span.clone(), found });
                     let state = rest.extra;
-                    Ok((rest, TokSpan::from_strspan(Token::Err, state, span)))
+                    Ok((rest, TokSpan::from_strspan(Token::Err, state, span, line)))
                 },
                 Err(e) => Err(e)
             },
@@ -155,6 +232,42 @@ fn lexer<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, Vec<TokSp
     )(input)
 }
 
+/// Unicode identifiers as in Rust, a `XID_Start` character or `_` followed by `XID_Continue` characters.
+/// Combining marks are `XID_Continue`, so decomposed accents are part of the name
+fn identifier<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, StrSpan<'a, 'b>> {
+    recognize(
+        pair(
+            satisfy(|c| is_xid_start(c) || c == '_'),
+            take_while(is_xid_continue)
+        )
+    )(input)
+}
+
+/// `/* ... */`, which can be nested. An unterminated comment is reported and goes until the end of the input
+fn block_comment<'a, 'b>(input: StrSpan<'a, 'b>) -> StrResult<StrSpan<'a, 'b>, StrSpan<'a, 'b>> {
+    let (_, opening): (_, StrSpan) = tag("/*")(input)?;
+    let text = input.fragment();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if text[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Ok(input.take_split(i))
+            }
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+
+    input.extra.report_error(ParserError::UnterminatedBlockComment { span: opening.span() });
+    Ok(input.take_split(text.len()))
+}
+
 fn recover_err<'a, 'b>(e: &ErrorTree<StrSpan<'a, 'b>>) -> StrSpan<'a, 'b> {
     match e {
         GenericErrorTree::Base { location, kind: _ } => *location,
@@ -163,16 +276,65 @@ fn recover_err<'a, 'b>(e: &ErrorTree<StrSpan<'a, 'b>>) -> StrSpan<'a, 'b> {
     }
 }
 
-pub fn lex<'a, 'b>(input: &'a str, errors: &'b RefCell<Vec<RecoveredError>>) -> Vec<TokSpan<'a, 'b>> {
-    let input = StrSpan::new_extra(input, ParseState(errors));
+pub fn lex<'a, 'b>(input: &'a str, file: FileId, errors: &'b RefCell<Vec<ParserError>>) -> Vec<TokSpan<'a, 'b>> {
+    let input = StrSpan::new_extra(input, ParseState(errors, file));
     let (_, tokens) = lexer(input).expect("Unrecovered error happenned in lexer");
 
     tokens
 }
 
-pub fn lex_tokens<'a, 'b>(input: &'a str, errors: &'b RefCell<Vec<RecoveredError>>) -> Vec<Token<'a>> {
-    let input = StrSpan::new_extra(input, ParseState(errors));
+pub fn lex_tokens<'a, 'b>(input: &'a str, file: FileId, errors: &'b RefCell<Vec<ParserError>>) -> Vec<Token<'a>> {
+    let input = StrSpan::new_extra(input, ParseState(errors, file));
     let (_, tokens) = lexer(input).expect("Unrecovered error happenned in lexer");
 
     tokens.into_iter().map(|a| *a.fragment()).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_block_comments() {
+        let errors = RefCell::new(Vec::new());
+        let tokens = lex_tokens("/* outer /* inner */ still a comment */\nclass A {}\n", 0, &errors);
+
+        assert!(errors.borrow().is_empty());
+        assert_eq!(tokens, [Token::K(Keyword::Class), Token::Ident("A"), Token::Separator('{'), Token::Separator('}')]);
+    }
+
+    #[test]
+    fn unterminated_block_comment_points_at_its_opening() {
+        let input = "class A {}\n/* open /* nested */\nclass B {}\n";
+        let errors = RefCell::new(Vec::new());
+        let tokens = lex_tokens(input, 0, &errors);
+
+        let start = input.find("/*").unwrap();
+        assert_eq!(*errors.borrow(), [ParserError::UnterminatedBlockComment { span: Span { file: 0, range: start..start+2 } }]);
+        assert_eq!(tokens.len(), 4);
+    }
+
+    #[test]
+    fn multibyte_identifier_byte_ranges() {
+        let input = "class Größe { x: Int § }\n";
+        let errors = RefCell::new(Vec::new());
+        let tokens = lex(input, 0, &errors);
+
+        assert_eq!(*tokens[1].fragment(), Token::Ident("Größe"));
+        assert_eq!(tokens[1].span().range, 6..13);
+
+        let start = input.find('§').unwrap();
+        let span = Span { file: 0, range: start..start+'§'.len_utf8() };
+        assert_eq!(*errors.borrow(), [ParserError::UnknownCharacter { span, found: '§' }]);
+    }
+
+    #[test]
+    fn decomposed_accent_stays_in_the_identifier() {
+        // `o` followed by U+0301 COMBINING ACUTE ACCENT
+        let errors = RefCell::new(Vec::new());
+        let tokens = lex_tokens("class Direccio\u{301}n {}\n", 0, &errors);
+
+        assert!(errors.borrow().is_empty());
+        assert_eq!(tokens[1], Token::Ident("Direccio\u{301}n"));
+    }
+}
@@ -1,23 +1,129 @@
+use std::fmt;
+
 use token::Spanned;
 
+pub use error::ParserError;
+pub use token::{FileId, Span};
+
 mod lexer;
 mod parser;
 mod recovery_err;
 mod token;
 mod combinators;
+mod error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found while reading a file, reported once the file has been parsed as far as possible
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    /// Code of the error kind, such as `E0003`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Other places related to the problem
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+/// A span of a diagnostic with the reason it's shown, a label on the main span explains it
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
 
-pub struct ParserError;
+impl From<ParserError> for Diagnostic {
+    fn from(error: ParserError) -> Diagnostic {
+        let notes = match error {
+            ParserError::UnterminatedBlockComment { .. } => vec!["block comments can be nested, each `/*` needs its own `*/`".to_string()],
+            _ => Vec::new(),
+        };
+        let labels = match &error {
+            ParserError::UnclosedBrace { opening, .. } => vec![Label {
+                span: opening.clone(),
+                message: "unclosed `{`".to_string()
+            }],
+            _ => Vec::new(),
+        };
 
+        Diagnostic {
+            span: error.span().clone(),
+            severity: error.severity(),
+            code: Some(error.code()),
+            message: error.to_string(),
+            labels,
+            notes
+        }
+    }
+}
+
+/// `name(args)` or `receiver.name(args)`
 #[derive(Debug)]
 pub struct FuncCall {
-    pub root: Spanned<String>,
-    pub access: Option<Spanned<String>>,
-    pub args: Vec<Spanned<String>>,
+    pub receiver: Option<Spanned<Value>>,
+    pub name: Spanned<String>,
+    pub args: Vec<Spanned<Value>>,
+}
+
+/// `object.member`
+#[derive(Debug)]
+pub struct MemberAccess {
+    pub object: Spanned<Value>,
+    pub member: Spanned<String>,
+}
+
+/// Used as call arguments and receivers, conditions and returned values, literals are kept as written
+#[derive(Debug)]
+pub enum Value {
+    Ident(String),
+    /// Without the quotes
+    Str(String),
+    Int(String),
+    Float(String),
+    Bool(bool),
+    Member(Box<MemberAccess>),
+    Call(Box<FuncCall>),
+}
+
+#[derive(Debug)]
+pub enum Type {
+    /// `Name` or `path::to::Name`, with optional generic arguments `Name<A, B>`
+    Path {
+        name: Spanned<String>,
+        generics: Vec<Spanned<Type>>
+    },
+    /// `&Type` or `&mut Type`
+    Reference {
+        mutable: bool,
+        inner: Box<Spanned<Type>>
+    },
+    /// `[Type]` or `[Type; len]`
+    Array {
+        inner: Box<Spanned<Type>>,
+        len: Option<Spanned<String>>
+    },
+    /// `(A, B)`
+    Tuple(Vec<Spanned<Type>>),
 }
 
 #[derive(Debug)]
 pub struct Assignment {
-    pub r#type: Option<Spanned<String>>,
+    pub r#type: Option<Spanned<Type>>,
     pub name: Spanned<String>,
     pub expr: Spanned<Expr>
 }
@@ -27,47 +133,280 @@ pub enum Expr {
     FuncCall(Box<FuncCall>),
     Assignment(Box<Assignment>),
     ExprList(Vec<Spanned<Self>>),
+    If(Box<If>),
+    While(Box<While>),
+    For(Box<For>),
+    /// `return` or `return value`
+    Return(Option<Spanned<Value>>),
     Error
 }
 
+/// `if condition { ... } else { ... }`, `else_branch` is an `Expr::If` for `else if`
+#[derive(Debug)]
+pub struct If {
+    pub condition: Spanned<Value>,
+    pub then_branch: Spanned<Expr>,
+    pub else_branch: Option<Spanned<Expr>>
+}
+
+/// `while condition { ... }`
+#[derive(Debug)]
+pub struct While {
+    pub condition: Spanned<Value>,
+    pub body: Spanned<Expr>
+}
+
+/// `for variable in iterable { ... }`
+#[derive(Debug)]
+pub struct For {
+    pub variable: Spanned<String>,
+    pub iterable: Spanned<Value>,
+    pub body: Spanned<Expr>
+}
+
 pub enum TopLevelStatement {
     Class(Class),
-    AnnotatedBlock(AnnotatedBlock)
+    Implementation(Implementation),
+    Enum(Enum),
+    Relationship(Relationship),
+    Package(Package),
+    Note(Note),
+    Import(Import),
+    /// Annotations followed by the statement they apply to
+    Annotated(Vec<Spanned<Annotation>>, Box<Spanned<TopLevelStatement>>),
+    /// A call outside of any method, only parsed after annotations (`@SequenceEntrypoint Shop.checkout()`)
+    Call(FuncCall)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelationshipKind {
+    /// `A -- B`
+    Association,
+    /// `A --> B`
+    DirectedAssociation,
+    /// `A o-- B`, A has B
+    Aggregation,
+    /// `A *-- B`, A is made of B
+    Composition,
+    /// `A ..> B`, A uses B
+    Dependency,
+}
+
+/// `Source "source_label" <kind> "target_label" Target : name`
+pub struct Relationship {
+    pub source: Spanned<String>,
+    pub source_label: Option<Spanned<String>>,
+    pub kind: Spanned<RelationshipKind>,
+    pub target_label: Option<Spanned<String>>,
+    pub target: Spanned<String>,
+    pub name: Option<Spanned<String>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClassKind {
+    Class,
+    Interface,
 }
 
 pub struct Class {
+    /// The `///` comments preceding the class, one line per comment
+    pub doc: Option<Spanned<String>>,
+    pub is_abstract: bool,
+    pub kind: ClassKind,
     pub name: Spanned<String>,
+    pub parents: Vec<Spanned<String>>,
+    pub interfaces: Vec<Spanned<String>>,
     pub attributes: Vec<Spanned<Attribute>>,
     pub methods: Vec<Spanned<Method>>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub enum Annotation {
-    SequenceEntrypoint,
+pub struct Enum {
+    pub doc: Option<Spanned<String>>,
+    pub name: Spanned<String>,
+    pub variants: Vec<Spanned<Variant>>,
+}
+
+pub struct Variant {
+    pub doc: Option<Spanned<String>>,
+    pub annotations: Vec<Spanned<Annotation>>,
+    pub name: Spanned<String>,
+    pub payload: VariantPayload,
+}
+
+pub enum VariantPayload {
+    /// `Variant`
+    Unit,
+    /// `Variant(A, B)`
+    Tuple(Vec<Spanned<Type>>),
+    /// `Variant { a: A, b: B }`
+    Struct(Vec<Spanned<Attribute>>),
+}
+
+/// `note "text"` or `note for Target "text"`
+pub struct Note {
+    pub target: Option<Spanned<NoteTarget>>,
+    pub text: Spanned<String>,
+}
+
+/// `Class` or `Class.member`
+pub struct NoteTarget {
+    pub class: Spanned<String>,
+    pub member: Option<Spanned<String>>,
+}
+
+/// `import "path/to/file.dia"`, only allowed outside of packages
+pub struct Import {
+    pub path: Spanned<String>,
+}
+
+/// `package name { ... }`, packages can be nested
+pub struct Package {
+    pub name: Spanned<String>,
+    pub statements: Vec<Spanned<TopLevelStatement>>,
+}
+
+pub struct Implementation {
+    pub interface: Spanned<String>,
+    pub implementor: Spanned<String>,
+    pub methods: Vec<Spanned<Method>>,
+}
+
+/// `@Name`, `@Name(arg)` or `@Name(key = value)`, which annotations exist and where they are allowed is checked after parsing
+#[derive(Debug)]
+pub struct Annotation {
+    pub name: Spanned<String>,
+    pub args: Vec<Spanned<AnnotationArg>>,
 }
 
-pub struct SequenceEntrypointBlock {
-    pub function: Spanned<FuncCall>
+/// `value` or `key = value`
+#[derive(Debug)]
+pub struct AnnotationArg {
+    pub key: Option<Spanned<String>>,
+    pub value: Spanned<Value>,
 }
 
-pub enum AnnotatedBlock {
-    SequenceEntrypoint(SequenceEntrypointBlock)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private,
+    Protected,
+    Package,
 }
 
 pub struct Method {
+    pub doc: Option<Spanned<String>>,
+    pub annotations: Vec<Spanned<Annotation>>,
+    pub visibility: Option<Spanned<Visibility>>,
+    pub is_static: bool,
+    pub is_abstract: bool,
     pub name: Spanned<String>,
     pub parameters: Vec<Spanned<Attribute>>,
-    pub ret_type: Option<Spanned<String>>,
+    pub ret_type: Option<Spanned<Type>>,
     pub body: Option<Spanned<Expr>>
 }
 
 pub struct Attribute {
+    pub doc: Option<Spanned<String>>,
+    pub annotations: Vec<Spanned<Annotation>>,
+    pub visibility: Option<Spanned<Visibility>>,
+    pub is_static: bool,
     pub name: Spanned<String>,
-    pub r#type: Option<Spanned<String>>
+    pub r#type: Option<Spanned<Type>>
 }
 
-pub fn tokenize(input: &str) -> Spanned<Result<Vec<Spanned<TopLevelStatement>>, ParserError>> {
+/// Parses a whole file, the statements containing errors are left out and the diagnostics explain why
+pub fn tokenize(input: &str, file: FileId) -> (Vec<Spanned<TopLevelStatement>>, Vec<Diagnostic>) {
     let errors = std::cell::RefCell::new(Vec::new());
-    let toks = lexer::lex(&input, &errors);
-    parser::token_parse(toks)
+    let toks = lexer::lex(&input, file, &errors);
+    let statements = parser::token_parse(toks);
+    let diagnostics = errors.into_inner().into_iter()
+        .map(Diagnostic::from)
+        .collect();
+
+    (statements, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(statement: &TopLevelStatement) -> &Class {
+        match statement {
+            TopLevelStatement::Class(class) => class,
+            _ => panic!("Expected a class"),
+        }
+    }
+
+    #[test]
+    fn member_typo_keeps_the_class() {
+        let input = "class Shop {\n    name: String\n    pub fn checkout(id: Int): {}\n    total: Int\n}\n";
+        let (statements, diagnostics) = tokenize(input, 0);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some("E0003"));
+        let start = input.find("{}").unwrap();
+        assert_eq!(diagnostics[0].span.range, start..start+1);
+
+        assert_eq!(statements.len(), 1);
+        let class = class(&statements[0].0);
+        assert_eq!(class.name.0, "Shop");
+        let attributes = class.attributes.iter()
+            .map(|(attribute, _)| attribute.name.0.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(attributes, ["name", "total"]);
+        assert!(class.methods.is_empty());
+    }
+
+    #[test]
+    fn doc_in_method_body_is_reported() {
+        let input = "class A {\n    fn run() {\n        /// first\n        self.go()\n        /// last\n    }\n}\n";
+        let (statements, diagnostics) = tokenize(input, 0);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.code == Some("W0001")));
+        let first = input.find(" first").unwrap();
+        assert_eq!(diagnostics[0].span.range, first..first+" first".len());
+        let last = input.find(" last").unwrap();
+        assert_eq!(diagnostics[1].span.range, last..last+" last".len());
+
+        let method = &class(&statements[0].0).methods[0].0;
+        match &method.body {
+            Some((Expr::ExprList(exprs), _)) => assert_eq!(exprs.len(), 1),
+            _ => panic!("Expected a method body"),
+        }
+    }
+
+    #[test]
+    fn doc_before_parameter_is_reported() {
+        let input = "class A {\n    fn run(\n        /// how many\n        n: Int\n    )\n}\n";
+        let (statements, diagnostics) = tokenize(input, 0);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some("W0001"));
+        let start = input.find(" how many").unwrap();
+        assert_eq!(diagnostics[0].span.range, start..start+" how many".len());
+
+        let method = &class(&statements[0].0).methods[0].0;
+        assert_eq!(method.parameters.len(), 1);
+        assert_eq!(method.parameters[0].0.name.0, "n");
+    }
+
+    #[test]
+    fn doc_before_struct_variant_field_is_reported() {
+        let input = "enum E {\n    V {\n        /// the x\n        x: Int\n    },\n}\n";
+        let (statements, diagnostics) = tokenize(input, 0);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some("W0001"));
+        let start = input.find(" the x").unwrap();
+        assert_eq!(diagnostics[0].span.range, start..start+" the x".len());
+
+        let TopLevelStatement::Enum(r#enum) = &statements[0].0 else {
+            panic!("Expected an enum")
+        };
+        match &r#enum.variants[0].0.payload {
+            VariantPayload::Struct(fields) => assert_eq!(fields.len(), 1),
+            _ => panic!("Expected a struct variant"),
+        }
+    }
 }
@@ -1,67 +1,268 @@
-use std::{ops::Range, io::ErrorKind};
+use nom::{branch::alt as choice, bytes::complete::{tag, take}, combinator::{map, map_res, opt, peek}, multi::{many0, many1, separated_list0, separated_list1}, sequence::{delimited, pair, preceded, terminated, tuple}, Parser};
+use nom_supreme::{error::BaseErrorKind, ParserExt};
 
-use nom::{branch::alt as choice, bytes::complete::{tag, take}, combinator::{map, map_res, opt}, multi::{many0, separated_list0}, sequence::{delimited, pair, preceded, terminated, tuple}};
-use nom_supreme::{error::{ErrorTree, BaseErrorKind}, ParserExt};
+use crate::{combinators::{map_with_span, spanned, to}, recovery_err::{found_token, next_span, non_opt, recover_many0, report, ToRange, TokError, TokResult}, token::{Keyword, Span, Spanned, TokSpan, Token, Tokens}, Annotation, AnnotationArg, Assignment, Attribute, Class, ClassKind, Enum, Expr, For, FuncCall, If, Implementation, Import, MemberAccess, Method, Note, NoteTarget, Package, ParserError, Relationship, RelationshipKind, TopLevelStatement, Type, Value, Variant, VariantPayload, Visibility, While};
 
-use crate::{combinators::{map_with_span, spanned}, recovery_err::{expression_recovery, non_opt, TokResult}, token::{Keyword, Spanned, Token, Tokens}, AnnotatedBlock, Annotation, Assignment, Attribute, Class, Expr, FuncCall, Method, ParserError, SequenceEntrypointBlock, TopLevelStatement};
+/// The error of the parsers taking a single token of a given kind
+fn expected_token(consumed: Tokens, expected: &'static str) -> ParserError {
+    ParserError::Expected {
+        span: consumed.span(),
+        expected,
+        found: found_token(&consumed)
+    }
+}
 
 pub(crate) fn ident<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<String>> {
     map_res(
-        spanned(take(1 as usize)),
-        |(consumed, span): (Tokens, Range<usize>)| {
+        spanned(take(1usize)),
+        |(consumed, span): (Tokens, Span)| {
             match consumed.tokens[0].fragment() {
                 Token::Ident(s) => Ok((String::from(*s), span)),
-                _ => Err(ErrorTree::Base {
-                    location: consumed,
-                    kind: BaseErrorKind::External(Box::new(tokio::io::Error::new(ErrorKind::Other, "Expected identifier")))
-                }) // TODO: Expand match tree for "expected: _, found X"
+                _ => Err(expected_token(consumed, "identifier"))
             }
         }
     )(input)
 }
 
-fn attribute<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Attribute>> {
+fn string<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<String>> {
+    map_res(
+        spanned(take(1usize)),
+        |(consumed, span): (Tokens, Span)| {
+            match consumed.tokens[0].fragment() {
+                Token::Str(s) => Ok((String::from(*s), span)),
+                _ => Err(expected_token(consumed, "string"))
+            }
+        }
+    )(input)
+}
+
+fn int<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<String>> {
+    map_res(
+        spanned(take(1usize)),
+        |(consumed, span): (Tokens, Span)| {
+            match consumed.tokens[0].fragment() {
+                Token::Int(s) => Ok((String::from(*s), span)),
+                _ => Err(expected_token(consumed, "integer"))
+            }
+        }
+    )(input)
+}
+
+fn literal<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Value>> {
+    map_res(
+        spanned(take(1usize)),
+        |(consumed, span): (Tokens, Span)| {
+            match consumed.tokens[0].fragment() {
+                Token::Str(s) => Ok((Value::Str(String::from(*s)), span)),
+                Token::Int(s) => Ok((Value::Int(String::from(*s)), span)),
+                Token::Float(s) => Ok((Value::Float(String::from(*s)), span)),
+                Token::Bool(b) => Ok((Value::Bool(*b), span)),
+                _ => Err(expected_token(consumed, "literal"))
+            }
+        }
+    )(input)
+}
+
+fn type_path<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<String>> {
+    map_with_span(
+        separated_list1(
+            pair(tag(Token::Separator(':')), tag(Token::Separator(':'))),
+            ident
+        ),
+        |segments, span| (
+            segments.into_iter()
+                .map(|(segment, _)| segment)
+                .collect::<Vec<_>>()
+                .join("::"),
+            span
+        )
+    )(input)
+}
+
+fn type_expr<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Type>> {
+    choice((
+        map_with_span(
+            pair(
+                type_path,
+                map(
+                    opt(delimited(
+                        tag(Token::Separator('<')),
+                        non_opt(separated_list1(tag(Token::Separator(',')), type_expr)),
+                        non_opt(tag(Token::Separator('>'))).context("`>`")
+                    )),
+                    Option::unwrap_or_default
+                )
+            ),
+            |(name, generics), span| (Type::Path {
+                name,
+                generics
+            }, span)
+        ),
+        map_with_span(
+            preceded(
+                tag(Token::Separator('&')),
+                pair(
+                    map(opt(tag(Token::K(Keyword::Mut))), |m| m.is_some()),
+                    non_opt(type_expr).context("type")
+                )
+            ),
+            |(mutable, inner), span| (Type::Reference {
+                mutable,
+                inner: Box::new(inner)
+            }, span)
+        ),
+        map_with_span(
+            delimited(
+                tag(Token::Separator('[')),
+                non_opt(pair(
+                    type_expr,
+                    opt(preceded(
+                        tag(Token::Separator(';')),
+                        non_opt(choice((int, ident)))
+                    ))
+                )),
+                non_opt(tag(Token::Separator(']'))).context("`]`")
+            ),
+            |(inner, len), span| (Type::Array {
+                inner: Box::new(inner),
+                len
+            }, span)
+        ),
+        map_with_span(
+            delimited(
+                tag(Token::Separator('(')),
+                terminated(
+                    separated_list0(tag(Token::Separator(',')), type_expr),
+                    opt(tag(Token::Separator(',')))
+                ),
+                non_opt(tag(Token::Separator(')'))).context("`)`")
+            ),
+            |types, span| (Type::Tuple(types), span)
+        ),
+    ))(input)
+}
+
+fn doc_line<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, String> {
+    map_res(
+        take(1usize),
+        |consumed: Tokens| {
+            match consumed.tokens[0].fragment() {
+                Token::Doc(s) => Ok(s.strip_prefix(' ').unwrap_or(s).trim_end().to_string()),
+                _ => Err(expected_token(consumed, "doc comment"))
+            }
+        }
+    )(input)
+}
+
+/// Consecutive `///` comments, joined with newlines
+fn doc_comment<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Option<Spanned<String>>> {
+    opt(map_with_span(
+        many1(doc_line),
+        |lines, span| (lines.join("\n"), span)
+    ))(input)
+}
+
+/// `///` comments where nothing keeps them, they are reported and skipped
+fn ignored_doc<'a, 'b: 'a>(ignored_by: &'static str) -> impl FnMut(Tokens<'a, 'b>) -> TokResult<'a, 'b, ()> {
+    move |input: Tokens<'a, 'b>| {
+        let (rest, doc) = doc_comment(input)?;
+        if let Some((_, span)) = doc {
+            report(&input, ParserError::IgnoredDocComment { span, ignored_by });
+        }
+        Ok((rest, ()))
+    }
+}
+
+fn visibility<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Visibility>> {
+    choice((
+        to(choice((tag(Token::Separator('+')), tag(Token::K(Keyword::Pub)))), Visibility::Public),
+        to(choice((tag(Token::Separator('-')), tag(Token::K(Keyword::Priv)))), Visibility::Private),
+        to(tag(Token::Separator('#')), Visibility::Protected),
+        to(tag(Token::Separator('~')), Visibility::Package),
+    ))(input)
+}
+
+fn parameter<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Attribute>> {
     map_with_span(
         pair(
             ident,
             opt(preceded(
                 tag(Token::Separator(':')),
-                non_opt(ident)
+                non_opt(type_expr).context("type")
             )),
         ),
         |(name, r#type), span| (Attribute {
+            doc: None,
+            annotations: Vec::new(),
+            visibility: None,
+            is_static: false,
             name,
             r#type
         }, span)
     )(input)
 }
 
+fn is_static<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, bool> {
+    map(opt(tag(Token::K(Keyword::Static))), |s| s.is_some())(input)
+}
+
+fn is_abstract<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, bool> {
+    map(opt(tag(Token::K(Keyword::Abstract))), |s| s.is_some())(input)
+}
+
+fn attribute<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Attribute>> {
+    map_with_span(
+        tuple((
+            doc_comment,
+            many0(annotation),
+            opt(visibility),
+            is_static,
+            parameter
+        )),
+        |(doc, annotations, visibility, is_static, (attribute, _)), span| (Attribute {
+            doc,
+            annotations,
+            visibility,
+            is_static,
+            ..attribute
+        }, span)
+    )(input)
+}
+
 fn method_definition<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Method>> {
     map_with_span(
-        preceded(
-            tag(Token::K(Keyword::Fn)),
+        pair(
+            terminated(
+                tuple((
+                    doc_comment,
+                    many0(annotation),
+                    opt(visibility),
+                    is_static,
+                    is_abstract
+                )),
+                tag(Token::K(Keyword::Fn))
+            ),
             tuple((
                 ident,
                 delimited(
                     tag(Token::Separator('(')),
-                    separated_list0(tag(Token::Separator(',')), attribute),
-                    tag(Token::Separator(')'))
+                    separated_list0(tag(Token::Separator(',')), preceded(ignored_doc("parameters"), parameter)),
+                    non_opt(tag(Token::Separator(')'))).context("`)`")
                 ),
                 opt(preceded(
                     tag(Token::Separator(':')),
-                    non_opt(ident)
+                    non_opt(type_expr).context("type")
                 )),
-                opt(preceded(
-                    tag(Token::Separator('{')),
-                    non_opt(terminated(
-                        method_body,
-                        tag(Token::Separator('}'))
-                    ))
-                ))
+                opt(braced(method_body))
             ))
         ),
-        |(name, parameters, ret_type, body), span| (
+        |((doc, annotations, visibility, is_static, is_abstract), (name, parameters, ret_type, body)), span| (
             Method {
+                doc,
+                annotations,
+                visibility,
+                is_static,
+                is_abstract,
                 name,
                 parameters,
                 ret_type,
@@ -70,110 +271,694 @@ fn method_definition<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned
     )(input)
 }
 
+fn call_args<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Vec<Spanned<Value>>> {
+    delimited(
+        tag(Token::Separator('(')),
+        separated_list0(tag(Token::Separator(',')), value),
+        non_opt(tag(Token::Separator(')'))).context("`)`")
+    )(input)
+}
+
+/// A literal, a variable or a free function call
+fn value_head<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Value>> {
+    choice((
+        literal,
+        map_with_span(
+            pair(ident, opt(call_args)),
+            |(name, args), span| match args {
+                Some(args) => (Value::Call(Box::new(FuncCall {
+                    receiver: None,
+                    name,
+                    args
+                })), span),
+                None => (Value::Ident(name.0), span),
+            }
+        )
+    ))(input)
+}
+
+/// The member name, with the call arguments if it's a method
+type Accessor = (Spanned<String>, Option<Vec<Spanned<Value>>>);
+
+/// `.member` or `.method(args)`
+fn value_accessor<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Accessor>> {
+    spanned(preceded(
+        tag(Token::Separator('.')),
+        pair(
+            non_opt(ident).context("member name"),
+            opt(call_args)
+        )
+    ))(input)
+}
+
+/// Member accesses and method calls chained on a value, `a.b.c(d.e())`
+fn value<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Value>> {
+    let (mut rest, mut value) = value_head(input)?;
+    loop {
+        match value_accessor(rest) {
+            Ok((r, ((name, args), accessor_span))) => {
+                let span = Span {
+                    file: accessor_span.file,
+                    range: value.1.range.start..accessor_span.range.end
+                };
+                value = match args {
+                    Some(args) => (Value::Call(Box::new(FuncCall {
+                        receiver: Some(value),
+                        name,
+                        args
+                    })), span),
+                    None => (Value::Member(Box::new(MemberAccess {
+                        object: value,
+                        member: name
+                    })), span),
+                };
+                rest = r;
+            },
+            Err(nom::Err::Error(_)) => return Ok((rest, value)),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 fn func_call<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<FuncCall>> {
+    let (rest, value) = value(input)?;
+    match value {
+        (Value::Call(f_call), span) => Ok((rest, (*f_call, span))),
+        _ => Err(nom::Err::Error(TokError::Base {
+            location: input,
+            kind: BaseErrorKind::External(ParserError::Expected {
+                span: input.span(),
+                expected: "function call",
+                found: found_token(&input)
+            })
+        })),
+    }
+}
+
+fn call_statement<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    map_with_span(
+        pair(
+            opt(terminated(
+                pair(
+                    ident,
+                    opt(preceded(
+                        tag(Token::Separator(':')),
+                        type_expr
+                    )),
+                ),
+                tag(Token::Op("="))
+            )),
+            func_call
+        ),
+        |(assignment, (f_call, f_call_span)), span| {
+            match assignment {
+                Some((assignment, r#type)) => (Expr::Assignment(Box::new(Assignment {
+                    name: assignment,
+                    expr: (Expr::FuncCall(Box::new(f_call)), f_call_span),
+                    r#type: r#type,
+                })), span),
+                None => (Expr::FuncCall(Box::new(f_call)), span)
+            }
+        }
+    )(input)
+}
+
+fn block<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    braced(method_body)(input)
+}
+
+/// `{ inner }`, once the `{` is found a missing `}` is reported along with it
+fn braced<'a, 'b: 'a, F, O>(mut inner: F) -> impl FnMut(Tokens<'a, 'b>) -> TokResult<'a, 'b, O>
+where
+    F: Parser<Tokens<'a, 'b>, O, TokError<'a, 'b>>
+{
+    move |input: Tokens<'a, 'b>| {
+        let (rest, opening) = tag(Token::Separator('{')).context("`{`").parse(input)?;
+        let (rest, inner) = inner.parse(rest)?;
+        match tag::<_, _, TokError>(Token::Separator('}'))(rest) {
+            Ok((rest, _)) => Ok((rest, inner)),
+            Err(nom::Err::Error(_)) => Err(nom::Err::Failure(TokError::Base {
+                location: rest,
+                kind: BaseErrorKind::External(ParserError::UnclosedBrace {
+                    span: next_span(&rest),
+                    opening: opening.span(),
+                    found: found_token(&rest)
+                })
+            })),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn if_expr<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
     map_with_span(
         tuple((
-            ident,
+            preceded(
+                tag(Token::K(Keyword::If)),
+                non_opt(value).context("if condition")
+            ),
+            non_opt(block).context("if block"),
             opt(preceded(
-                tag(Token::Separator('.')),
-                non_opt(ident)
+                tag(Token::K(Keyword::Else)),
+                non_opt(choice((if_expr, block))).context("else block")
+            ))
+        )),
+        |(condition, then_branch, else_branch), span| (Expr::If(Box::new(If {
+            condition,
+            then_branch,
+            else_branch
+        })), span)
+    )(input)
+}
+
+fn while_expr<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    map_with_span(
+        pair(
+            preceded(
+                tag(Token::K(Keyword::While)),
+                non_opt(value).context("while condition")
+            ),
+            non_opt(block).context("while block")
+        ),
+        |(condition, body), span| (Expr::While(Box::new(While {
+            condition,
+            body
+        })), span)
+    )(input)
+}
+
+fn for_expr<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    map_with_span(
+        tuple((
+            preceded(
+                tag(Token::K(Keyword::For)),
+                non_opt(ident).context("for variable")
+            ),
+            preceded(
+                non_opt(tag(Token::K(Keyword::In))).context("`in`"),
+                non_opt(value).context("for iterable")
+            ),
+            non_opt(block).context("for block")
+        )),
+        |(variable, iterable, body), span| (Expr::For(Box::new(For {
+            variable,
+            iterable,
+            body
+        })), span)
+    )(input)
+}
+
+/// The returned value starts on the line of the `return`, a bare `return` ends at the line break
+fn return_expr<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    map_with_span(
+        |input: Tokens<'a, 'b>| {
+            let (rest, keyword) = tag(Token::K(Keyword::Return))(input)?;
+            match rest.tokens.first() {
+                Some(next) if next.location_line() == keyword.tokens[0].location_line() => opt(value)(rest),
+                _ => Ok((rest, None)),
+            }
+        },
+        |value, span| (Expr::Return(value), span)
+    )(input)
+}
+
+/// Tokens after which a statement of a method body can start
+fn starts_body_statement(previous: &Token, next: &[TokSpan]) -> bool {
+    match next[0].fragment() {
+        Token::K(Keyword::If | Keyword::While | Keyword::For | Keyword::Return) => true,
+        Token::Ident(_) => matches!(previous, Token::Separator(')' | '}')),
+        _ => false
+    }
+}
+
+/// Statements have no doc, a `///` in a body is reported and skipped
+fn method_body<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+    let (rest, (exprs, span)) = spanned(recover_many0(
+        preceded(
+            ignored_doc("statements"),
+            choice((
+                if_expr,
+                while_expr,
+                for_expr,
+                return_expr,
+                call_statement
+            ))
+        ),
+        starts_body_statement
+    ))(input)?;
+
+    // The `///` right before the `}` are skipped by `recover_many0`, no statement ends with one
+    let consumed = &input.tokens[..input.tokens.len() - rest.tokens.len()];
+    let trailing = consumed.iter().rev().take_while(|token| matches!(token.fragment(), Token::Doc(_))).count();
+    if trailing > 0 {
+        let first = consumed[consumed.len() - trailing].span();
+        let last = consumed[consumed.len() - 1].span();
+        report(&input, ParserError::IgnoredDocComment {
+            span: Span {
+                file: first.file,
+                range: first.range.start..last.range.end
+            },
+            ignored_by: "statements"
+        });
+    }
+
+    Ok((rest, (Expr::ExprList(exprs), span)))
+}
+
+fn parent_list<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Vec<Spanned<String>>> {
+    preceded(
+        choice((
+            tag(Token::Separator(':')),
+            tag(Token::K(Keyword::Extends))
+        )),
+        non_opt(separated_list1(tag(Token::Separator(',')), type_path)).context("parent class")
+    )(input)
+}
+
+fn interface_list<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Vec<Spanned<String>>> {
+    preceded(
+        tag(Token::K(Keyword::Implements)),
+        non_opt(separated_list1(tag(Token::Separator(',')), type_path)).context("implemented interface")
+    )(input)
+}
+
+fn class_kind<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, ClassKind> {
+    choice((
+        map(tag(Token::K(Keyword::Class)), |_| ClassKind::Class),
+        map(tag(Token::K(Keyword::Interface)), |_| ClassKind::Interface),
+    ))(input)
+}
+
+/// The attributes and methods of a class, each in the order they are written
+type Members = (Vec<Spanned<Attribute>>, Vec<Spanned<Method>>);
+
+enum Member {
+    Attribute(Spanned<Attribute>),
+    Method(Spanned<Method>)
+}
+
+/// Tokens after which an attribute or a method can start
+fn starts_member(previous: &Token, next: &[TokSpan]) -> bool {
+    match next[0].fragment() {
+        Token::Doc(_) | Token::A(_) => true,
+        Token::K(Keyword::Fn | Keyword::Pub | Keyword::Priv | Keyword::Static | Keyword::Abstract) => true,
+        Token::Separator('+' | '-' | '#' | '~') => true,
+        // `name: Type`, but not a `path::Type`
+        Token::Ident(_) => matches!(previous, Token::Separator('}')) || (
+            next.get(1).map(|token| *token.fragment()) == Some(Token::Separator(':'))
+            && next.get(2).map(|token| *token.fragment()) != Some(Token::Separator(':'))
+        ),
+        _ => false
+    }
+}
+
+/// Attributes and methods, in any order
+fn members<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Members> {
+    map(
+        recover_many0(
+            choice((
+                map(method_definition, Member::Method),
+                map(attribute, Member::Attribute)
             )),
-            delimited(
-                tag(Token::Separator('(')),
-                separated_list0(tag(Token::Separator(',')), ident),
-                tag(Token::Separator(')'))
-            )
+            starts_member
+        ),
+        |members| {
+            let mut attributes = Vec::new();
+            let mut methods = Vec::new();
+            for member in members {
+                match member {
+                    Member::Attribute(attribute) => attributes.push(attribute),
+                    Member::Method(method) => methods.push(method),
+                }
+            }
+            (attributes, methods)
+        }
+    )(input)
+}
+
+fn class_definition<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Class>> {
+    map_with_span(
+        tuple((
+            is_abstract,
+            class_kind.context("tag class"),
+            ident.context("class name"),
+            map(opt(parent_list), Option::unwrap_or_default),
+            map(opt(interface_list), Option::unwrap_or_default),
+            braced(members)
         )),
-        |(root, access, args), span| (FuncCall {
-            root,
-            access,
-            args
+        |(is_abstract, kind, name, parents, interfaces, (attributes, methods)), span| (Class {
+            doc: None,
+            is_abstract,
+            kind,
+            name,
+            parents,
+            interfaces,
+            attributes,
+            methods
         }, span)
     )(input)
 }
 
-fn method_body<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Expr>> {
+fn variant<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Variant>> {
     map_with_span(
-        many0(map_with_span(
-            pair(
-                opt(terminated(
-                    pair(
-                        ident,
-                        opt(preceded(
-                            tag(Token::Separator(':')),
-                            ident
+        tuple((
+            doc_comment,
+            many0(annotation),
+            ident,
+            map(
+                opt(choice((
+                    map(
+                        delimited(
+                            tag(Token::Separator('(')),
+                            terminated(
+                                separated_list0(tag(Token::Separator(',')), type_expr),
+                                opt(tag(Token::Separator(',')))
+                            ),
+                            non_opt(tag(Token::Separator(')'))).context("`)`")
+                        ),
+                        VariantPayload::Tuple
+                    ),
+                    map(
+                        braced(terminated(
+                            separated_list0(tag(Token::Separator(',')), preceded(ignored_doc("struct variant fields"), parameter)),
+                            opt(tag(Token::Separator(',')))
                         )),
+                        VariantPayload::Struct
                     ),
-                    tag(Token::Op("="))
-                )),
-                func_call
-            ),
-            |(assignment, (f_call, f_call_span)), span| {
-                match assignment {
-                    Some((assignment, r#type)) => (Expr::Assignment(Box::new(Assignment {
-                        name: assignment,
-                        expr: (Expr::FuncCall(Box::new(f_call)), f_call_span),
-                        r#type: r#type,
-                    })), span),
-                    None => (Expr::FuncCall(Box::new(f_call)), span)
-                }
-            }
+                ))),
+                |payload| payload.unwrap_or(VariantPayload::Unit)
+            )
         )),
-        |funs, span| (Expr::ExprList(funs), span)
+        |(doc, annotations, name, payload), span| (Variant {
+            doc,
+            annotations,
+            name,
+            payload
+        }, span)
     )(input)
 }
 
-fn class_definition<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Class>> {
+/// Tokens after which a variant can start
+fn starts_variant(previous: &Token, next: &[TokSpan]) -> bool {
+    match next[0].fragment() {
+        Token::Doc(_) | Token::A(_) => true,
+        Token::Ident(_) => matches!(previous, Token::Separator(',' | ')' | '}')),
+        _ => false
+    }
+}
+
+/// The `,` after a variant, only the last one can leave it out.
+/// A missing one is reported without losing the variant before it
+fn variant_separator<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, ()> {
+    match choice((tag(Token::Separator(',')), peek(tag(Token::Separator('}')))))(input) {
+        Ok((rest, _)) => Ok((rest, ())),
+        Err(nom::Err::Error(_)) => {
+            // At the end of the file the missing `}` is reported instead
+            if !input.tokens.is_empty() {
+                report(&input, ParserError::Expected {
+                    span: next_span(&input),
+                    expected: "`,`",
+                    found: found_token(&input)
+                });
+            }
+            Ok((input, ()))
+        },
+        Err(e) => Err(e)
+    }
+}
+
+fn enum_definition<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Enum>> {
     map_with_span(
         pair(
             preceded(
-                tag(Token::K(Keyword::Class)).context("tag class"),
-                ident.context("class name")
+                tag(Token::K(Keyword::Enum)).context("tag enum"),
+                ident.context("enum name")
             ),
-            delimited(
-                tag(Token::Separator('{')).context("Opening brack"),
-                pair(
-                    many0(attribute),
-                    many0(method_definition)
-                ),
-                tag(Token::Separator('}')).context("Closing brack")
-            )
+            braced(recover_many0(
+                terminated(variant, variant_separator),
+                starts_variant
+            ))
         ),
-        |(name, (attributes, methods)), span| (Class {
+        |(name, variants), span| (Enum {
+            doc: None,
             name,
-            attributes,
+            variants
+        }, span)
+    )(input)
+}
+
+fn relationship_kind<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<RelationshipKind>> {
+    choice((
+        to(tag(Token::Op("--")), RelationshipKind::Association),
+        to(tag(Token::Op("-->")), RelationshipKind::DirectedAssociation),
+        to(tag(Token::Op("o--")), RelationshipKind::Aggregation),
+        to(tag(Token::Op("*--")), RelationshipKind::Composition),
+        to(tag(Token::Op("..>")), RelationshipKind::Dependency),
+    ))(input)
+}
+
+fn relationship<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Relationship>> {
+    map_with_span(
+        tuple((
+            type_path,
+            opt(string),
+            relationship_kind,
+            opt(string),
+            non_opt(type_path).context("relationship target"),
+            opt(preceded(
+                tag(Token::Separator(':')),
+                non_opt(choice((string, ident))).context("relationship name")
+            ))
+        )),
+        |(source, source_label, kind, target_label, target, name), span| (Relationship {
+            source,
+            source_label,
+            kind,
+            target_label,
+            target,
+            name
+        }, span)
+    )(input)
+}
+
+fn implementation<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Implementation>> {
+    map_with_span(
+        tuple((
+            preceded(
+                tag(Token::K(Keyword::Impl)).context("tag impl"),
+                type_path.context("interface name")
+            ),
+            preceded(
+                tag(Token::K(Keyword::For)).context("tag for"),
+                type_path.context("implementor name")
+            ),
+            map(
+                opt(braced(recover_many0(method_definition, starts_member))),
+                Option::unwrap_or_default
+            )
+        )),
+        |(interface, implementor, methods), span| (Implementation {
+            interface,
+            implementor,
             methods
         }, span)
     )(input)
 }
 
-fn annotation_entrypoint<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<SequenceEntrypointBlock>> {
+fn annotation_name<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<String>> {
+    map_res(
+        spanned(take(1usize)),
+        |(consumed, span): (Tokens, Span)| {
+            match consumed.tokens[0].fragment() {
+                Token::A(s) => Ok((String::from(*s), span)),
+                _ => Err(expected_token(consumed, "annotation"))
+            }
+        }
+    )(input)
+}
+
+/// `value` or `key = value`
+fn annotation_arg<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<AnnotationArg>> {
+    map_with_span(
+        pair(
+            opt(terminated(ident, tag(Token::Op("=")))),
+            non_opt(value).context("annotation argument")
+        ),
+        |(key, value), span| (AnnotationArg {
+            key,
+            value
+        }, span)
+    )(input)
+}
+
+/// `@Name` or `@Name(args)`
+fn annotation<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Annotation>> {
+    map_with_span(
+        pair(
+            annotation_name,
+            map(
+                opt(delimited(
+                    tag(Token::Separator('(')),
+                    separated_list0(tag(Token::Separator(',')), annotation_arg),
+                    non_opt(tag(Token::Separator(')'))).context("`)`")
+                )),
+                Option::unwrap_or_default
+            )
+        ),
+        |(name, args), span| (Annotation {
+            name,
+            args
+        }, span)
+    )(input)
+}
+
+/// Annotations followed by a statement or by a call
+fn annotated_statement<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<TopLevelStatement>> {
+    map_with_span(
+        pair(
+            many1(annotation),
+            non_opt(choice((
+                map(func_call, |(call, s)| (TopLevelStatement::Call(call), s)),
+                item
+            ))).context("annotated statement")
+        ),
+        |(annotations, statement), span| (TopLevelStatement::Annotated(annotations, Box::new(statement)), span)
+    )(input)
+}
+
+fn package<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Package>> {
+    map_with_span(
+        pair(
+            preceded(
+                tag(Token::K(Keyword::Package)).context("tag package"),
+                non_opt(ident).context("package name")
+            ),
+            braced(recover_many0(top_level_statement, starts_statement))
+        ),
+        |(name, statements), span| (Package {
+            name,
+            statements
+        }, span)
+    )(input)
+}
+
+fn note_target<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<NoteTarget>> {
+    map_with_span(
+        pair(
+            type_path,
+            opt(preceded(
+                tag(Token::Separator('.')),
+                non_opt(ident).context("member name")
+            ))
+        ),
+        |(class, member), span| (NoteTarget {
+            class,
+            member
+        }, span)
+    )(input)
+}
+
+fn note<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Note>> {
     map_with_span(
         preceded(
-            tag(Token::A(Annotation::SequenceEntrypoint)).context("tag sequence_entrypoint"),
-            func_call
+            tag(Token::K(Keyword::Note)).context("tag note"),
+            pair(
+                opt(preceded(
+                    tag(Token::K(Keyword::For)),
+                    non_opt(note_target).context("note target")
+                )),
+                non_opt(string).context("note text")
+            )
         ),
-        |func_call, span| (SequenceEntrypointBlock {
-            function: func_call
+        |(target, text), span| (Note {
+            target,
+            text
         }, span)
     )(input)
 }
 
-fn annotation_block<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<AnnotatedBlock>> {
+/// A top level statement without annotations
+fn item<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<TopLevelStatement>> {
     choice((
-        map(annotation_entrypoint, |(entry_point_block, span)| (AnnotatedBlock::SequenceEntrypoint(entry_point_block), span)),
+        map(class_definition, |(class, s)| (TopLevelStatement::Class(class), s)),
+        map(implementation, |(implementation, s)| (TopLevelStatement::Implementation(implementation), s)),
+        map(enum_definition, |(r#enum, s)| (TopLevelStatement::Enum(r#enum), s)),
+        map(package, |(package, s)| (TopLevelStatement::Package(package), s)),
+        map(note, |(note, s)| (TopLevelStatement::Note(note), s)),
+        map(relationship, |(relationship, s)| (TopLevelStatement::Relationship(relationship), s)),
     ))(input)
 }
 
-pub(crate) fn statements<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Result<Vec<Spanned<TopLevelStatement>>, ParserError>>> {
-    expression_recovery(map_with_span(
-        many0(choice((
-            map(class_definition, |(class, s)| (TopLevelStatement::Class(class), s)),
-            map(annotation_block, |(annotated, s)| (TopLevelStatement::AnnotatedBlock(annotated), s)),
-        ))),
-        |v, span| (
-                    Ok(v),
-                    span
-                )
-    ))(input)
+/// Doc comments are written before the annotations, they are kept on classes and enums and reported elsewhere
+fn top_level_statement<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<TopLevelStatement>> {
+    let (rest, ((doc, (mut statement, _)), span)) = spanned(
+        pair(
+            doc_comment,
+            choice((
+                annotated_statement,
+                item
+            ))
+        )
+    )(input)?;
+
+    if let Some(warning) = document(&mut statement, doc) {
+        report(&input, warning);
+    }
+
+    Ok((rest, (statement, span)))
+}
+
+/// The warning for a doc the statement can't keep
+fn document(statement: &mut TopLevelStatement, doc: Option<Spanned<String>>) -> Option<ParserError> {
+    let ignored_by = match statement {
+        TopLevelStatement::Class(class) => {
+            class.doc = doc;
+            return None
+        },
+        TopLevelStatement::Enum(r#enum) => {
+            r#enum.doc = doc;
+            return None
+        },
+        TopLevelStatement::Annotated(_, inner) => return document(&mut inner.0, doc),
+        TopLevelStatement::Implementation(_) => "impl blocks",
+        TopLevelStatement::Package(_) => "packages",
+        TopLevelStatement::Note(_) => "notes",
+        TopLevelStatement::Relationship(_) => "relationships",
+        TopLevelStatement::Import(_) => "imports",
+        TopLevelStatement::Call(_) => "calls",
+    };
+
+    doc.map(|(_, span)| ParserError::IgnoredDocComment { span, ignored_by })
+}
+
+fn import<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Import>> {
+    map_with_span(
+        preceded(
+            tag(Token::K(Keyword::Import)).context("tag import"),
+            non_opt(string).context("imported file path")
+        ),
+        |path, span| (Import {
+            path
+        }, span)
+    )(input)
+}
+
+/// Tokens a top level statement can start with
+fn starts_statement(previous: &Token, next: &[TokSpan]) -> bool {
+    match next[0].fragment() {
+        Token::Doc(_) | Token::A(_) => true,
+        Token::K(Keyword::Import | Keyword::Class | Keyword::Interface | Keyword::Abstract | Keyword::Enum | Keyword::Impl | Keyword::Package | Keyword::Note) => true,
+        Token::Ident(_) => matches!(previous, Token::Separator('}')),
+        _ => false
+    }
+}
+
+pub(crate) fn statements<'a, 'b>(input: Tokens<'a, 'b>) -> TokResult<'a, 'b, Vec<Spanned<TopLevelStatement>>> {
+    recover_many0(
+        choice((
+            map(import, |(import, s)| (TopLevelStatement::Import(import), s)),
+            top_level_statement
+        )),
+        starts_statement
+    )(input)
 }
@@ -1,20 +1,20 @@
-use std::{cell::RefCell, ops::Range, error::Error};
+use std::cell::RefCell;
 
-use nom::{Parser, InputTake, combinator::peek, bytes::complete::tag as just};
+use nom::{Parser, InputTake};
 use nom_locate::LocatedSpan;
 //use nom::error::{ParseError, ErrorKind, FromExternalError};
-use nom_supreme::error::{ErrorTree, GenericErrorTree};
+use nom_supreme::error::{BaseErrorKind, ErrorTree, GenericErrorTree, StackContext};
 
-use crate::{token::{TokSpan, Tokens, Spanned, Token}, ParserError};
+use crate::{token::{FileId, Span, TokSpan, Tokens, Token}, ParserError};
 
 pub type StrResult<I, O, E=ErrorTree<I>> = Result<(I, O), nom::Err<E>>;
-pub type TokError<'a, 'b> = GenericErrorTree<Tokens<'a, 'b>, &'a [TokSpan<'a, 'b>], &'static str, Box<dyn Error + 'a>>;
+pub type TokError<'a, 'b> = GenericErrorTree<Tokens<'a, 'b>, &'a [TokSpan<'a, 'b>], &'static str, ParserError>;
 pub type TokResult<'a, 'b, O, I=Tokens<'a, 'b>, E=TokError<'a, 'b>> = Result<(I, O), nom::Err<E>>;
 
 /// Carried around in the `LocatedSpan::extra` field in
 /// between `nom` parsers.
 #[derive(Clone, Copy, Debug)]
-pub struct ParseState<'a>(pub &'a RefCell<Vec<RecoveredError>>);
+pub struct ParseState<'a>(pub &'a RefCell<Vec<ParserError>>, pub FileId);
 
 unsafe impl<'a> Sync for ParseState<'a> {
 
@@ -23,100 +23,167 @@ unsafe impl<'a> Sync for ParseState<'a> {
 impl<'a> ParseState<'a> {
     /// Pushes an error onto the errors stack from within a `nom`
     /// parser combinator while still allowing parsing to continue.
-    pub fn report_error(&self, error: RecoveredError) {
+    pub fn report_error(&self, error: ParserError) {
         self.0.borrow_mut().push(error);
     }
 }
 
-/// Error containing a text span and an error message to display.
-#[derive(Debug)]
-pub struct RecoveredError(pub Range<usize>, pub String);
-
 pub type StrSpan<'a, 'b> = LocatedSpan<&'a str, ParseState<'b>>;
 
 pub trait ToRange {
-    fn span(&self) -> Range<usize>;
-    fn consumed_span(&self, next_start: usize) -> Range<usize>;
+    fn span(&self) -> Span;
+    fn consumed_span(&self, next_start: usize) -> Span;
 }
 
 impl<'a, 'b> ToRange for StrSpan<'a, 'b> {
-    fn span(&self) -> Range<usize> {
-        let start = self.get_column_first_line()-1;
-        start..start+self.fragment().chars().count()
+    fn span(&self) -> Span {
+        let start = self.location_offset();
+        Span {
+            file: self.extra.1,
+            range: start..start+self.fragment().len()
+        }
     }
 
     #[allow(unused_variables)]
-    fn consumed_span(&self, next_start: usize) -> Range<usize> {
+    fn consumed_span(&self, next_start: usize) -> Span {
         unimplemented!()
     }
 }
 
-pub fn expression_recovery<'a, 'b: 'a, F, Ex>(mut func: F) -> impl FnMut(Tokens<'a, 'b>) -> TokResult<'a, 'b, Spanned<Result<Ex, ParserError>>>
+/// Parses `item`s until the `}` closing the enclosing block or the end of the file.
+/// An item that can't be parsed is reported and skipped until a token accepted by `is_sync`
+/// outside of any bracket, so the items after it are still parsed.
+/// `///` comments right before the end document nothing, they are skipped like other comments
+pub fn recover_many0<'a, 'b: 'a, F, O, S>(mut item: F, is_sync: S) -> impl FnMut(Tokens<'a, 'b>) -> TokResult<'a, 'b, Vec<O>>
 where
-    F: Parser<Tokens<'a, 'b>, Spanned<Result<Ex, ParserError>>, TokError<'a, 'b>>
+    F: Parser<Tokens<'a, 'b>, O, TokError<'a, 'b>>,
+    S: Fn(&Token, &[TokSpan]) -> bool
 {
-    move |input: Tokens<'a, 'b>| -> TokResult<'a, 'b, Spanned<Result<Ex, ParserError>>> {
-        match func.parse(input) {
-            Ok(r) => Ok(r),
-            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
-                recover_from_error(e)
-            },
-            Err(e) => Err(e)
+    move |mut input: Tokens<'a, 'b>| -> TokResult<'a, 'b, Vec<O>> {
+        let mut items = Vec::new();
+        loop {
+            let docs = input.tokens.iter().take_while(|token| matches!(token.fragment(), Token::Doc(_))).count();
+            match input.tokens.get(docs).map(|token| *token.fragment()) {
+                None | Some(Token::Separator('}')) => return Ok((input.take_split(docs).0, items)),
+                _ => ()
+            }
+
+            match item.parse(input) {
+                Ok((rest, _)) if rest.tokens.len() == input.tokens.len() => return Ok((input, items)),
+                Ok((rest, parsed)) => {
+                    items.push(parsed);
+                    input = rest;
+                },
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => {
+                    let error = parser_error(&e);
+                    let error_start = error.span().range.start;
+                    report(&input, error);
+                    input = skip_to_sync(input, error_start, &is_sync);
+                },
+                Err(e) => return Err(e)
+            }
         }
     }
 }
 
-fn recover_from_error<'a, 'b, Ex>(e: TokError<'a, 'b>) -> TokResult<'a, 'b, Spanned<Result<Ex, ParserError>>> {
-    match e {
-        GenericErrorTree::Stack { base: _, contexts } => {
-            let (input, context) = contexts[contexts.len()-1];
-            let (rest, span) = match input.tokens.len() {
-                0 => {
-                    (input, input.span())
-                },
-                _ => {
-                    let (mut rest, input) = input.take_split(1);
-                    let mut next_token = input;
-                    let mut continue_loop = |input| -> TokResult<'a, 'b, Tokens> {
-                        peek(just(Token::Separator(';')))(input) // TODO: Also end loop on Token::Separator('}')
-                    }(rest).is_err() && rest.tokens.len() > 0;
-                    while continue_loop { // TODO: First see where to split and then do it, instead of splitting by 1 at a time
-                        let (r, n_token) = next_token.take_split(1);
-                        rest = r;
-                        next_token = n_token;
-                        continue_loop = |input| -> TokResult<'a, 'b, Tokens> {
-                            peek(just(Token::Separator(';')))(input) // TODO: Also end loop on Token::Separator('}'), but count how many { are opened while searching for }
-                        }(rest).is_err() && rest.tokens.len() > 0;
-                    }
-                    (rest, input.span())
+/// Skips at least one token, then stops before the first token accepted by `is_sync` that isn't inside braces
+/// opened while skipping, or before the `}` closing the enclosing block.
+/// Tokens before `error_start` parsed fine, they can't be synchronized on or the same error would be found again
+fn skip_to_sync<'a, 'b, S>(input: Tokens<'a, 'b>, error_start: usize, is_sync: &S) -> Tokens<'a, 'b>
+where
+    S: Fn(&Token, &[TokSpan]) -> bool
+{
+    let mut open_brackets = Vec::new();
+    let mut skipped = 0;
+    for (i, token) in input.tokens.iter().enumerate() {
+        let after_error = token.location_offset() >= error_start;
+        let token = *token.fragment();
+        // Inside parentheses only keywords can be synchronized on, so an unclosed one can't hide the rest of the block
+        let in_block = open_brackets.contains(&'{');
+        let in_parentheses = !open_brackets.is_empty();
+        if i > 0 && after_error && !in_block && !(in_parentheses && matches!(token, Token::Ident(_))) && is_sync(input.tokens[i-1].fragment(), &input.tokens[i..]) {
+            break
+        }
+
+        match token {
+            Token::Separator(c @ ('{' | '(' | '[')) => open_brackets.push(c),
+            // An unclosed parenthesis can't hide the end of the block
+            Token::Separator('}') => match open_brackets.iter().rposition(|c| *c == '{') {
+                Some(open) => open_brackets.truncate(open),
+                None if i > 0 => break,
+                None => ()
+            },
+            Token::Separator(c @ (')' | ']')) => {
+                let open = if c == ')' { '(' } else { '[' };
+                if open_brackets.last() == Some(&open) {
+                    open_brackets.pop();
                 }
-            };
+            },
+            _ => ()
+        }
+        skipped = i + 1;
+    }
+
+    input.take_split(skipped).0
+}
 
-            match context {
-                nom_supreme::error::StackContext::Context(error_msg) => rest.state.report_error(RecoveredError(span.clone(), error_msg.to_string())),
-                _ => unreachable!()
-            };
+/// Unknown characters were already reported by the lexer, they don't get a second error
+pub fn report(input: &Tokens, error: ParserError) {
+    let lexer_error = input.tokens.iter().any(|token| {
+        *token.fragment() == Token::Err && token.span() == *error.span()
+    });
+    if !lexer_error {
+        input.state.report_error(error)
+    }
+}
 
-            Ok((rest, (Err(ParserError), span)))
+/// The error shown to the user for a parser that failed, the furthest one when several alternatives failed
+pub fn parser_error(e: &TokError) -> ParserError {
+    match e {
+        GenericErrorTree::Base { location: _, kind: BaseErrorKind::External(error) } => error.clone(),
+        GenericErrorTree::Base { location, kind: _ } => ParserError::UnexpectedToken {
+            span: next_span(location),
+            found: found_token(location)
         },
-        GenericErrorTree::Base { location, kind } => Err(nom::Err::Error(TokError::Base { location, kind })),
-        GenericErrorTree::Alt(v) => {
-            let mut v2 = vec![];
-            for e in v {
-                match recover_from_error(e) {
-                    Ok(a) => return Ok(a),
-                    Err(res) => match res {
-                        nom::Err::Error(res) => v2.push(res),
-                        nom::Err::Failure(res) => v2.push(res),
-                        _ => unreachable!(),
-                    },
-                };
-            };
-            Err(nom::Err::Error(TokError::Alt(v2)))
+        GenericErrorTree::Stack { base, contexts } => {
+            let error = parser_error(base);
+            // The innermost context describes what was expected, unless the error happened after it
+            match contexts.first() {
+                Some((location, StackContext::Context(expected))) if next_span(location).range.start >= error.span().range.start => ParserError::Expected {
+                    span: next_span(location),
+                    expected,
+                    found: found_token(location)
+                },
+                _ => error
+            }
+        },
+        GenericErrorTree::Alt(alternatives) => {
+            let errors: Vec<_> = alternatives.iter().map(parser_error).collect();
+            let furthest = errors.iter().map(|error| error.span().range.start).max().unwrap_or_default();
+            let mut furthest_errors = errors.into_iter().filter(|error| error.span().range.start == furthest);
+            let error = furthest_errors.next().expect("Alt errors have alternatives");
+            match (furthest_errors.next(), error) {
+                // No alternative got further than the others, none of them was expected more
+                (Some(_), ParserError::Expected { span, expected: _, found }) => ParserError::UnexpectedToken { span, found },
+                (_, error) => error
+            }
         },
     }
 }
 
+/// Span of the first token of `input`, or the end of the file
+pub fn next_span(input: &Tokens) -> Span {
+    match input.tokens.first() {
+        Some(token) => token.span(),
+        None => input.span(),
+    }
+}
+
+/// The first token of `input`, `None` at the end of the file
+pub fn found_token(input: &Tokens) -> Option<String> {
+    input.tokens.first().map(|token| token.fragment().to_string())
+}
+
 pub fn non_opt<'a, 'b: 'a, F, I, O>(mut func: F) -> impl FnMut(I) -> TokResult<'a, 'b, O, I>
 where
     F: Parser<I, O, TokError<'a, 'b>>,
@@ -3,17 +3,24 @@ use std::{ops::Range, iter::{Enumerate, Copied}, slice::Iter, fmt::{self, Displa
 use nom::{Compare, CompareResult, InputLength, InputIter, InputTake, Needed};
 use nom_locate::LocatedSpan;
 
-use crate::{recovery_err::{ParseState, ToRange}, Annotation};
+use crate::recovery_err::{ParseState, ToRange};
 
 #[derive(Clone, Debug, Copy, PartialEq)]
 pub enum Token<'a> {
     K(Keyword),
     Op(&'a str),
     Ident(&'a str),
+    Int(&'a str),
+    Float(&'a str),
+    Bool(bool),
+    Str(&'a str),
     Separator(char),
     Comment(&'a str),
+    /// `/// text`, without the slashes
+    Doc(&'a str),
     Err,
-    A(Annotation),
+    /// `@Name`, without the `@`
+    A(&'a str),
 }
 
 impl Token<'_> {
@@ -31,6 +38,10 @@ impl<'a> fmt::Display for Token<'a> {
             Token::Op(s) => write!(f, "{}", s),
             Token::Separator(c) => write!(f, "{}", c),
             Token::Ident(s) => write!(f, "{}", s),
+            Token::Int(s) => write!(f, "{}", s),
+            Token::Float(s) => write!(f, "{}", s),
+            Token::Bool(b) => write!(f, "{}", b),
+            Token::Str(s) => write!(f, "\"{}\"", s),
             Token::K(k) => match k {
                 Keyword::Fn => write!(f, "fn"),
                 Keyword::If => write!(f, "if"),
@@ -39,12 +50,25 @@ impl<'a> fmt::Display for Token<'a> {
                 Keyword::For => write!(f, "for"),
                 Keyword::In => write!(f, "in"),
                 Keyword::Class => write!(f, "class"),
+                Keyword::Extends => write!(f, "extends"),
+                Keyword::Interface => write!(f, "interface"),
+                Keyword::Implements => write!(f, "implements"),
+                Keyword::Impl => write!(f, "impl"),
+                Keyword::Pub => write!(f, "pub"),
+                Keyword::Priv => write!(f, "priv"),
+                Keyword::Static => write!(f, "static"),
+                Keyword::Abstract => write!(f, "abstract"),
+                Keyword::Mut => write!(f, "mut"),
+                Keyword::Enum => write!(f, "enum"),
+                Keyword::Package => write!(f, "package"),
+                Keyword::Note => write!(f, "note"),
+                Keyword::Return => write!(f, "return"),
+                Keyword::Import => write!(f, "import"),
             },
             Token::Comment(s) => write!(f, "{}", s),
+            Token::Doc(s) => write!(f, "///{}", s),
             Token::Err => write!(f, "Err"),
-            Token::A(a) => match a {
-                Annotation::SequenceEntrypoint => write!(f, "@SequenceEntrypoint"),
-            },
+            Token::A(s) => write!(f, "@{}", s),
         }
     }
 }
@@ -57,38 +81,65 @@ pub enum Keyword {
     While,
     For,
     In,
-    Class
+    Class,
+    Extends,
+    Interface,
+    Implements,
+    Impl,
+    Pub,
+    Priv,
+    Static,
+    Abstract,
+    Mut,
+    Enum,
+    Package,
+    Note,
+    Return,
+    Import
+}
+
+/// Index of a source file, chosen by the caller of `tokenize`
+pub type FileId = usize;
+
+/// Location of a parsed element, `range` is in bytes from the start of the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub file: FileId,
+    pub range: Range<usize>,
 }
 
-pub type Spanned<T> = (T, Range<usize>);
+pub type Spanned<T> = (T, Span);
 
 pub type TokSpan<'a, 'b> = LocatedSpan<Token<'a>, (ParseState<'b>, usize)>;
 
 pub trait FromStrSpan<'a, 'b> {
-    fn from_strspan(token: Token<'a>, state: ParseState<'b>, span: Range<usize>) -> TokSpan<'a, 'b>;
+    fn from_strspan(token: Token<'a>, state: ParseState<'b>, span: Span, line: u32) -> TokSpan<'a, 'b>;
 }
 
 impl<'a, 'b> FromStrSpan<'a, 'b> for TokSpan<'a, 'b> {
     #[inline]
-    fn from_strspan(token: Token<'a>, state: ParseState<'b>, span: Range<usize>) -> TokSpan<'a, 'b> {
-        unsafe{TokSpan::new_from_raw_offset(span.start, 0, token, (state, span.end-span.start))}
+    fn from_strspan(token: Token<'a>, state: ParseState<'b>, span: Span, line: u32) -> TokSpan<'a, 'b> {
+        unsafe{TokSpan::new_from_raw_offset(span.range.start, line, token, (state, span.range.end-span.range.start))}
     }
 }
 
 impl<'a, 'b> ToRange for TokSpan<'a, 'b> {
-    fn span(&self) -> Range<usize> {
+    fn span(&self) -> Span {
         let start = self.location_offset();
-        start..start+self.extra.1
+        Span {
+            file: self.extra.0.1,
+            range: start..start+self.extra.1
+        }
     }
 
     #[allow(unused_variables)]
-    fn consumed_span(&self, next_start: usize) -> Range<usize> {
+    fn consumed_span(&self, next_start: usize) -> Span {
         unimplemented!()
     }
 }
 
 impl<'a, 'b> ToRange for Tokens<'a, 'b> {
-    fn span(&self) -> Range<usize> {
+    fn span(&self) -> Span {
         let start = self.offset;
         let end = match self.tokens.len() {
             0 => start+1, // TODO: Find out why the "+1". Find out why self.tokens.len() would be 0 in the first place
@@ -97,22 +148,28 @@ impl<'a, 'b> ToRange for Tokens<'a, 'b> {
                 end.location_offset()+end.extra.1
             }
         };
-        start..end
+        Span {
+            file: self.state.1,
+            range: start..end
+        }
     }
 
-    fn consumed_span(&self, next_start: usize) -> Range<usize> {
-        let start = self.span().start;
+    fn consumed_span(&self, next_start: usize) -> Span {
+        let start = self.span().range.start;
         let mut end = start;
         for token in self.tokens {
             let tok_span = token.span();
-            if tok_span.start >= next_start {
+            if tok_span.range.start >= next_start {
                 break
             } else {
-                end = tok_span.end
+                end = tok_span.range.end
             }
         }
 
-        start..end
+        Span {
+            file: self.state.1,
+            range: start..end
+        }
     }
 }
 
@@ -195,7 +252,7 @@ impl<'a> InputLength for Token<'a> {
 
 impl<'a, 'b> InputTake for Tokens<'a, 'b> {
     fn take(&self, count: usize) -> Self {
-        Tokens::new(&self.tokens[0..count], self.state)
+        Tokens{tokens: &self.tokens[0..count], offset: self.offset, state: self.state}
     }
 
     fn take_split(&self, count: usize) -> (Self, Self) {
@@ -203,9 +260,9 @@ impl<'a, 'b> InputTake for Tokens<'a, 'b> {
         let suf_offset = match suffix.len() {
             0 => match prefix.len() {
                 0 => self.offset,
-                _ => prefix[0].span().end
+                _ => prefix[prefix.len()-1].span().range.end
             },
-            _ => suffix[0].span().start
+            _ => suffix[0].span().range.start
         };
         (Tokens{tokens: suffix, offset: suf_offset, state: self.state}, Tokens{tokens: prefix, offset: self.offset, state: self.state})
     }